@@ -0,0 +1,59 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use chrono::Local;
+use log::warn;
+
+/// An append-only log of all operations that modified the database.
+/// Each line contains a timestamp, the statement and the affected transaction ids.
+pub(crate) struct AuditLog {
+    file_path: PathBuf,
+}
+
+impl AuditLog {
+    pub(crate) fn new(file_path: PathBuf) -> AuditLog {
+        AuditLog { file_path }
+    }
+
+    /// Append an entry to audit log. Failing to write audit log does not fail the operation, only a warning is logged.
+    pub(crate) fn record(&self, statement: &str, trans_ids: &[u32]) {
+        let statement = statement.replace('\n', " ");
+        let trans_ids = trans_ids.iter().map(u32::to_string).collect::<Vec<String>>().join(",");
+        let entry = format!("{} | {} | {}\n", Local::now().format("%Y-%m-%d %H:%M:%S"), statement, trans_ids);
+
+        let result = OpenOptions::new().create(true).append(true).open(&self.file_path)
+            .and_then(|mut file| file.write_all(entry.as_bytes()));
+        if let Err(e) = result {
+            warn!("Unable to write audit log {}: {}", self.file_path.display(), e);
+        }
+    }
+
+    /// Return the most recent entries, oldest first
+    pub(crate) fn tail(&self, count: usize) -> Vec<String> {
+        let content = fs::read_to_string(&self.file_path).unwrap_or_default();
+        let lines: Vec<&str> = content.lines().collect();
+        let start = lines.len().saturating_sub(count);
+        lines[start..].iter().map(|l| l.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audit::AuditLog;
+
+    #[test]
+    fn test() {
+        let file_path = std::env::temp_dir().join(format!("perfidb_audit_{}.log", std::process::id()));
+        let audit_log = AuditLog::new(file_path.clone());
+        audit_log.record("LABEL 1 2 food", &[1, 2]);
+        audit_log.record("DELETE 3", &[3]);
+
+        let entries = audit_log.tail(1);
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].ends_with(" | DELETE 3 | 3"));
+        assert_eq!(audit_log.tail(10).len(), 2);
+
+        std::fs::remove_file(file_path).unwrap();
+    }
+}
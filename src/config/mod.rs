@@ -1,16 +1,81 @@
 use std::fs;
 use std::path::Path;
+use crossterm::style::Color;
 use serde::{Serialize, Deserialize};
 use toml::value::Table;
 
+/// Max number of characters of descriptions printed in tables if not configured
+const DEFAULT_DESCRIPTION_WIDTH: usize = 50;
+
+/// Colours of labels without a configured colour. Red and green are left out, they are used for amounts.
+const LABEL_PALETTE: [Color; 8] = [Color::Cyan, Color::Magenta, Color::Yellow, Color::Blue,
+    Color::DarkCyan, Color::DarkMagenta, Color::DarkYellow, Color::DarkBlue];
+
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct Config {
-    pub(crate) labels: Table
+    /// Currency of transactions without one, e.g. when exporting to beancount
+    #[serde(default)]
+    pub(crate) default_currency: Option<String>,
+
+    /// The dir that contains bank transaction csv files, used if --import-root-dir is not specified
+    #[serde(default)]
+    pub(crate) import_root_dir: Option<String>,
+
+    /// Account of statement files placed directly in the import root dir, 'default' if not set
+    #[serde(default)]
+    pub(crate) default_account: Option<String>,
+
+    /// Do not print number of transactions and total amount below `SELECT *` results
+    #[serde(default)]
+    pub(crate) hide_select_footer: bool,
+
+    /// Max number of characters of descriptions in `SELECT` results and live edit, longer ones are cut with '…'
+    #[serde(default)]
+    pub(crate) description_width: Option<usize>,
+
+    /// Show months as '2023-03' instead of 'Mar 2023' in `GROUP BY month` results
+    #[serde(default)]
+    pub(crate) iso_months: bool,
+
+    pub(crate) labels: Table,
+
+    /// Monthly spending limit of each label
+    #[serde(default)]
+    pub(crate) budgets: Table,
+
+    /// Whether statements of an account have positive amount as spending, keyed by account name
+    #[serde(default)]
+    pub(crate) inverse_amount: Table,
+
+    /// Colour of labels in `SELECT` results and live edit, keyed by label, e.g. grocery = "green"
+    #[serde(default)]
+    pub(crate) label_colors: Table,
 }
 
 impl Config {
     pub(crate) fn empty() -> Config {
-        Config { labels: Table::new() }
+        Config { default_currency: None, import_root_dir: None, default_account: None, hide_select_footer: false, description_width: None, iso_months: false, labels: Table::new(), budgets: Table::new(), inverse_amount: Table::new(), label_colors: Table::new() }
+    }
+
+    pub(crate) fn description_width(&self) -> usize {
+        self.description_width.unwrap_or(DEFAULT_DESCRIPTION_WIDTH)
+    }
+
+    /// Configured inverse amount setting of an account, if any
+    pub(crate) fn inverse_amount_of(&self, account: &str) -> Option<bool> {
+        self.inverse_amount.get(account).and_then(|value| value.as_bool())
+    }
+
+    /// Colour of a label, the configured one if any, otherwise picked by a hash of the label so that
+    /// a label always has the same colour
+    pub(crate) fn label_color(&self, label: &str) -> Color {
+        self.label_colors.get(label)
+            .and_then(|value| value.as_str())
+            .and_then(|color| Color::try_from(color).ok())
+            .unwrap_or_else(|| {
+                let hash = label.bytes().fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32));
+                LABEL_PALETTE[hash as usize % LABEL_PALETTE.len()]
+            })
     }
 
     pub(crate) fn load_from_file(file_path: &str) -> Config {
@@ -24,3 +89,19 @@ impl Config {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crossterm::style::Color;
+    use crate::config::Config;
+
+    #[test]
+    fn test_label_color() {
+        let config: Config = toml::from_str("[labels]\n[label_colors]\ngrocery = 'green'\nfood = 'no-such-colour'").unwrap();
+        assert_eq!(config.label_color("grocery"), Color::Green);
+
+        // Unconfigured labels, or ones with an invalid colour, get a colour by hash, the same every time
+        assert_eq!(config.label_color("food"), config.label_color("food"));
+        assert_eq!(config.label_color("travel"), Config::empty().label_color("travel"));
+        assert_ne!(config.label_color("travel"), Color::Green);
+    }
+}
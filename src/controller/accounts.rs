@@ -0,0 +1,28 @@
+use comfy_table::{Cell, CellAlignment, Table, TableComponent};
+use log::info;
+use crate::controller::select::format_amount;
+use crate::db::Database;
+
+/// Print all accounts with their number of transactions and net amount
+pub(crate) fn execute_accounts(db: &Database) {
+    let accounts = db.list_accounts();
+    if accounts.is_empty() {
+        info!("No accounts. Accounts are created when transactions are imported or inserted.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table.remove_style(TableComponent::HorizontalLines);
+    table.remove_style(TableComponent::MiddleIntersections);
+    table.remove_style(TableComponent::LeftBorderIntersections);
+    table.remove_style(TableComponent::RightBorderIntersections);
+    table.set_header(vec!["Account", "Transactions", "Net"]);
+    for a in accounts {
+        table.add_row(vec![
+            Cell::new(a.account),
+            Cell::new(a.transactions).set_alignment(CellAlignment::Right),
+            Cell::new(format_amount(a.net)).set_alignment(CellAlignment::Right),
+        ]);
+    }
+    println!("{table}");
+}
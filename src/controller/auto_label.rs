@@ -0,0 +1,70 @@
+use crate::db::Database;
+use crate::db::label_op::LabelCommand;
+use crate::labeller::Labeller;
+use crate::parser::{Condition, Operator, OrderBy};
+use crate::transaction::Transaction;
+
+/// Execute an AUTO statement on transactions matching the condition. If `is_run` is true the transactions are
+/// labelled, otherwise they are left unchanged. Returns the transactions with their new labels, to be displayed.
+pub(crate) fn execute_auto_label(db: &mut Database, condition: Condition, is_run: bool, label_cmd: LabelCommand, labeller: &Labeller) -> Vec<Transaction> {
+    // Labelled transactions are not changed, so they are not shown either
+    let condition = match label_cmd {
+        LabelCommand::AutoUnlabelled => Condition::And(Box::new((condition, Condition::Label(Operator::IsNull, "".into())))),
+        _ => condition
+    };
+
+    let mut transactions = db.query(None, Some(condition), OrderBy::date(), None);
+    if is_run {
+        let trans_ids: Vec<u32> = transactions.iter().map(|t| t.id).collect();
+        for trans_id in &trans_ids {
+            db.apply_label_ops(*trans_id, label_cmd.clone(), labeller);
+        }
+        db.save();
+        // Not running the condition again, transactions labelled by ONLY UNLABELLED no longer match it
+        return db.query(None, Some(Condition::IdIn(trans_ids)), OrderBy::date(), None);
+    }
+
+    for t in transactions.iter_mut() {
+        let new_labels = labeller.label(&t.description);
+        match label_cmd {
+            LabelCommand::AutoAdd => {
+                for label in new_labels {
+                    if !t.labels.contains(&label) {
+                        t.labels.push(label);
+                    }
+                }
+            },
+            _ => t.labels = new_labels
+        }
+    }
+    transactions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::controller::auto_label::execute_auto_label;
+    use crate::db::label_op::LabelCommand;
+    use crate::db::test_db;
+    use crate::labeller::Labeller;
+    use crate::parser::{Condition, Operator};
+
+    #[test]
+    fn test_auto_only_unlabelled() {
+        let mut db = test_db();
+        db.disable_autosave();
+        let config: Config = toml::from_str("[labels]\nincome = \"salary\"\nsupermarket = \"coles|woolworths\"").unwrap();
+        let labeller = Labeller::new(&config);
+        let all = || Condition::DescriptionLength(Operator::Gt, 0);
+
+        // Preview shows the labels unlabelled transactions would get
+        let preview = execute_auto_label(&mut db, all(), false, LabelCommand::AutoUnlabelled, &labeller);
+        assert_eq!(preview.iter().map(|t| (t.id, t.labels.clone())).collect::<Vec<_>>(), vec![(2, vec!["income".to_string()])]);
+        assert!(db.find_by_id(2).labels.is_empty());
+
+        // Transactions just labelled are shown, even though they are no longer unlabelled
+        let labelled = execute_auto_label(&mut db, all(), true, LabelCommand::AutoUnlabelled, &labeller);
+        assert_eq!(labelled.iter().map(|t| (t.id, t.labels.clone())).collect::<Vec<_>>(), vec![(2, vec!["income".to_string()])]);
+        assert_eq!(db.find_by_id(1).labels, vec!["grocery"]);
+    }
+}
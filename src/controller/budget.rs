@@ -0,0 +1,52 @@
+use std::ops::Range;
+use chrono::NaiveDate;
+use comfy_table::{Cell, CellAlignment, Color, Table, TableComponent};
+use log::{info, warn};
+use toml::Value;
+use crate::controller::select::{format_amount, label_totals};
+use crate::db::Database;
+use crate::parser::{Condition, LabelAmount, Operator, OrderBy};
+
+/// Print spending of each budgeted label in the given month, and how much of the budget is remaining
+pub(crate) fn execute_budget(db: &mut Database, month: Range<NaiveDate>, config_file: &str) {
+    let config = db.config(config_file);
+    if config.budgets.is_empty() {
+        info!("No budgets defined in {config_file}");
+        return;
+    }
+
+    let transactions = db.query(None, Some(Condition::Date(Operator::Eq, month.clone())), OrderBy::date(), None);
+    let totals = label_totals(&transactions, LabelAmount::Full);
+
+    let mut table = Table::new();
+    table.remove_style(TableComponent::HorizontalLines);
+    table.remove_style(TableComponent::MiddleIntersections);
+    table.remove_style(TableComponent::LeftBorderIntersections);
+    table.remove_style(TableComponent::RightBorderIntersections);
+    table.set_header(vec!["Label", "Budget", "Spent", "Remaining"]);
+    // toml table is sorted by key, so labels are listed in alphabetical order
+    for (label, limit) in &config.budgets {
+        let limit = match limit {
+            Value::Integer(limit) => *limit as f32,
+            Value::Float(limit) => *limit as f32,
+            _ => {
+                warn!("Budget of '{label}' is not a number");
+                continue;
+            }
+        };
+
+        // Spending has negative amount
+        let spent = totals.iter().find(|(l, _)| l.eq_ignore_ascii_case(label)).map(|(_, total)| -total).unwrap_or(0.0);
+        let remaining = limit - spent;
+        let color = if remaining < 0.0 { Color::Red } else { Color::Green };
+        table.add_row(vec![
+            Cell::new(label),
+            Cell::new(format_amount(limit)).set_alignment(CellAlignment::Right),
+            Cell::new(format_amount(spent)).set_alignment(CellAlignment::Right),
+            Cell::new(format_amount(remaining)).set_alignment(CellAlignment::Right).fg(color),
+        ]);
+    }
+
+    println!("Budget for {}", month.start.format("%Y-%m"));
+    println!("{table}");
+}
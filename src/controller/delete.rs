@@ -0,0 +1,52 @@
+use comfy_table::{Cell, CellAlignment, Table, TableComponent};
+use crate::controller::select::{format_amount, format_date};
+use crate::db::Database;
+use crate::transaction::Transaction;
+
+/// Max number of transactions printed when asking to confirm a delete
+const DELETE_SAMPLE_SIZE: usize = 5;
+
+/// Execute a DELETE statement. Unless `force` is true, the transactions to delete are printed and user is asked to
+/// confirm. Returns ids of the deleted transactions, or None if user cancelled the delete.
+pub(crate) fn execute_delete(db: &mut Database, trans_ids: &[u32], force: bool) -> Option<Vec<u32>> {
+    let transactions: Vec<Transaction> = trans_ids.iter().filter_map(|id| db.search_by_id(*id)).collect();
+    if transactions.is_empty() {
+        return Some(vec![]);
+    }
+
+    if !force && !confirm_delete(&transactions) {
+        return None;
+    }
+    Some(db.delete(trans_ids))
+}
+
+/// Print number of transactions to delete with some of them, and ask user whether to delete them
+fn confirm_delete(transactions: &[Transaction]) -> bool {
+    let mut table = Table::new();
+    table.remove_style(TableComponent::HorizontalLines);
+    table.remove_style(TableComponent::MiddleIntersections);
+    table.remove_style(TableComponent::LeftBorderIntersections);
+    table.remove_style(TableComponent::RightBorderIntersections);
+    table.set_header(vec!["ID", "Account", "Date", "Description", "Amount"]);
+    for t in transactions.iter().take(DELETE_SAMPLE_SIZE) {
+        table.add_row(vec![
+            Cell::new(t.id),
+            Cell::new(&t.account),
+            Cell::new(format_date(t.date)),
+            Cell::new(&t.description),
+            Cell::new(format_amount(t.amount)).set_alignment(CellAlignment::Right),
+        ]);
+    }
+    println!("{table}");
+    if transactions.len() > DELETE_SAMPLE_SIZE {
+        println!("... and {} more", transactions.len() - DELETE_SAMPLE_SIZE);
+    }
+
+    println!("{} transactions will be deleted. This can not be undone.\n\
+    Use DELETE ... (force) to skip this confirmation.\n\
+    yes or no, default is 'no': ", transactions.len());
+
+    let mut user_input = String::new();
+    std::io::stdin().read_line(&mut user_input).unwrap();
+    user_input.trim().to_lowercase() == "yes"
+}
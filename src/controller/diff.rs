@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::path::Path;
+use comfy_table::{Cell, CellAlignment, Table, TableComponent};
+use log::{info, warn};
+use crate::controller::select::{format_amount, format_date};
+use crate::csv_reader;
+use crate::csv_reader::Record;
+use crate::db::Database;
+use crate::transaction::transaction_hash;
+
+/// Compare rows of a statement file with transactions in the database, without importing the file.
+/// A row is considered already imported if a transaction has the same date, description and amount.
+pub(crate) fn execute_diff(db: &Database, file_path: &str) {
+    let records: Vec<Record> = match csv_reader::stream_transactions("", Path::new(file_path)).and_then(|records| records.collect()) {
+        Ok(records) => records,
+        Err(e) => {
+            warn!("Unable to read {file_path}: {e}");
+            return;
+        }
+    };
+
+    let hashes = db.transaction_hashes();
+    let mut table = Table::new();
+    table.remove_style(TableComponent::HorizontalLines);
+    table.remove_style(TableComponent::MiddleIntersections);
+    table.remove_style(TableComponent::LeftBorderIntersections);
+    table.remove_style(TableComponent::RightBorderIntersections);
+    table.set_header(vec!["Date", "Description", "Amount", "Status"]);
+    let mut new_count = 0;
+    for (r, existing) in diff(&records, &hashes) {
+        let status = match existing {
+            Some(trans_id) => format!("exists, id {trans_id}"),
+            None => {
+                new_count += 1;
+                "new".to_string()
+            }
+        };
+        table.add_row(vec![
+            Cell::new(format_date(r.date)),
+            Cell::new(&r.description),
+            Cell::new(format_amount(r.amount)).set_alignment(CellAlignment::Right),
+            Cell::new(status),
+        ]);
+    }
+
+    println!("{table}");
+    info!("{new_count} new, {} already in database", records.len() - new_count);
+}
+
+/// Pair each record with the id of the transaction having the same content, if any
+fn diff<'a>(records: &'a [Record], hashes: &HashMap<u64, u32>) -> Vec<(&'a Record, Option<u32>)> {
+    records.iter().map(|r| (r, hashes.get(&transaction_hash(r.date, &r.description, r.amount)).copied())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::controller::diff::diff;
+    use crate::csv_reader::Record;
+    use crate::transaction::transaction_hash;
+
+    #[test]
+    fn test_diff() {
+        let record = |description: &str, amount: f32| Record::new("", "2023-01-05", description, amount);
+        let records = vec![record("Woolworths", -50.0), record("Coles", -20.0)];
+        // Already imported with inverted amount
+        let hashes = HashMap::from([(transaction_hash(records[0].date, "Woolworths", 50.0), 7)]);
+
+        let results: Vec<Option<u32>> = diff(&records, &hashes).into_iter().map(|(_, existing)| existing).collect();
+        assert_eq!(results, vec![Some(7), None]);
+    }
+}
@@ -1,13 +1,307 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
 use csv::WriterBuilder;
+use rusqlite::{params, Connection};
+use crate::controller::select::{account_totals, format_amount, format_date, label_totals, month_totals, sort_groups, week_totals};
 use crate::db::Database;
-use crate::parser::OrderBy;
+use crate::parser::{Condition, GroupBy, OrderBy};
+use crate::transaction::Transaction;
+
+/// Currency used in beancount export if neither transaction nor config has one
+const DEFAULT_BEANCOUNT_CURRENCY: &str = "USD";
+
+/// Export transactions to a file. If `GROUP BY` is specified, export total amount of each group instead.
+/// Files with `.beancount` extension are exported in beancount plain-text accounting format, files with `.json`
+/// extension are exported as a json array which can be imported again, files with `.md` extension are exported as a
+/// Markdown table, files with `.sqlite` or `.sqlite3` extension are exported as a SQLite database.
+pub(crate) fn execute_export_db(db : &mut Database, file_path :&str, condition: Option<Condition>, group_by: Option<GroupBy>, config_file: &str) {
+    let transactions = db.query(None, condition, OrderBy::date(), None);
+    if file_path.ends_with(".beancount") {
+        let config = db.config(config_file);
+        let default_currency = config.default_currency.as_deref().unwrap_or(DEFAULT_BEANCOUNT_CURRENCY);
+        fs::write(file_path, to_beancount(&transactions, default_currency)).unwrap();
+        return;
+    }
+    if file_path.ends_with(".sqlite") || file_path.ends_with(".sqlite3") {
+        to_sqlite(&transactions, file_path).unwrap();
+        return;
+    }
+    if file_path.ends_with(".md") {
+        fs::write(file_path, to_markdown(&transactions, group_by)).unwrap();
+        return;
+    }
+    if file_path.ends_with(".json") && group_by.is_none() {
+        fs::write(file_path, serde_json::to_string_pretty(&transactions).unwrap()).unwrap();
+        return;
+    }
 
-/// Export transactions to a file
-pub(crate) fn execute_export_db(db : &mut Database, file_path :&str) {
-    let transactions = db.query(None, None, OrderBy::date(), None);
     let mut csv_writer = WriterBuilder::new().has_headers(true).from_path(file_path).unwrap();
-    for t in transactions {
-        csv_writer.serialize(t).unwrap();
+    match group_by {
+        Some(GroupBy::Label(label_amount, order)) => {
+            csv_writer.write_record(["label", "total"]).unwrap();
+            let mut totals = label_totals(&transactions, label_amount);
+            sort_groups(&mut totals, None, order);
+            for (label, total) in totals {
+                csv_writer.write_record([label, format!("{total:.2}").as_str()]).unwrap();
+            }
+        },
+        Some(GroupBy::Account(order)) => {
+            csv_writer.write_record(["account", "total"]).unwrap();
+            let mut totals = account_totals(&transactions);
+            sort_groups(&mut totals, None, order);
+            for (account, total) in totals {
+                csv_writer.write_record([account, format!("{total:.2}").as_str()]).unwrap();
+            }
+        },
+        Some(GroupBy::Week) => {
+            csv_writer.write_record(["week", "total"]).unwrap();
+            for (week, total) in week_totals(&transactions) {
+                csv_writer.write_record([week.as_str(), format!("{total:.2}").as_str()]).unwrap();
+            }
+        },
+        // Exported months are always in ISO format, e.g. 2023-03, so they are easy to process
+        Some(GroupBy::Month) => {
+            csv_writer.write_record(["month", "total"]).unwrap();
+            for (month, total) in month_totals(&transactions) {
+                csv_writer.write_record([month.format("%Y-%m").to_string().as_str(), format!("{total:.2}").as_str()]).unwrap();
+            }
+        },
+        None => {
+            for t in transactions {
+                csv_writer.serialize(t).unwrap();
+            }
+        }
     }
     csv_writer.flush().unwrap();
-}
\ No newline at end of file
+}
+
+/// Convert transactions to a GitHub-flavored Markdown table, with the same columns as `SELECT *`,
+/// or the total amount of each group if `GROUP BY` is specified.
+fn to_markdown(transactions: &[Transaction], group_by: Option<GroupBy>) -> String {
+    let (header, rows): (Vec<&str>, Vec<Vec<String>>) = match group_by {
+        Some(GroupBy::Label(label_amount, order)) => {
+            let mut totals = label_totals(transactions, label_amount);
+            sort_groups(&mut totals, None, order);
+            (vec!["Label", "Amount"], totals.into_iter().map(|(label, total)| vec![label.to_string(), format_amount(total)]).collect())
+        },
+        Some(GroupBy::Account(order)) => {
+            let mut totals = account_totals(transactions);
+            sort_groups(&mut totals, None, order);
+            (vec!["Account", "Amount"], totals.into_iter().map(|(account, total)| vec![account.to_string(), format_amount(total)]).collect())
+        },
+        Some(GroupBy::Week) => (vec!["Week", "Amount"],
+            week_totals(transactions).into_iter().map(|(week, total)| vec![week, format_amount(total)]).collect()),
+        Some(GroupBy::Month) => (vec!["Month", "Amount"],
+            month_totals(transactions).into_iter().map(|(month, total)| vec![month.format("%Y-%m").to_string(), format_amount(total)]).collect()),
+        None => (vec!["ID", "Account", "Date", "Description", "Amount", "Labels"],
+            transactions.iter().map(|t| vec![t.id.to_string(), t.account.clone(), format_date(t.date),
+                                             t.description.clone(), format_amount(t.amount), t.tags_display()]).collect()),
+    };
+
+    let mut output = format!("| {} |\n", header.join(" | "));
+    // Amount column is right aligned
+    let alignments: Vec<&str> = header.iter().map(|h| if *h == "Amount" { "---:" } else { "---" }).collect();
+    output.push_str(&format!("| {} |\n", alignments.join(" | ")));
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+        output.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    output
+}
+
+/// Write transactions to a new SQLite database, replacing the file if it exists. Transactions are stored in a
+/// `transactions` table, and their labels in a `labels` table, one row per transaction and label.
+fn to_sqlite(transactions: &[Transaction], file_path: &str) -> rusqlite::Result<()> {
+    if Path::new(file_path).exists() {
+        fs::remove_file(file_path).unwrap();
+    }
+
+    let mut conn = Connection::open(file_path)?;
+    conn.execute_batch("
+        CREATE TABLE transactions (
+            id INTEGER PRIMARY KEY,
+            account TEXT NOT NULL,
+            date TEXT NOT NULL,
+            description TEXT NOT NULL,
+            amount REAL NOT NULL,
+            currency TEXT
+        );
+        CREATE TABLE labels (
+            transaction_id INTEGER NOT NULL REFERENCES transactions(id),
+            label TEXT NOT NULL,
+            PRIMARY KEY (transaction_id, label)
+        );
+        CREATE INDEX labels_label ON labels(label);")?;
+
+    // Insert everything in a single SQLite transaction, otherwise each insert is committed to disk separately
+    let tx = conn.transaction()?;
+    {
+        let mut insert_transaction = tx.prepare("INSERT INTO transactions VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+        let mut insert_label = tx.prepare("INSERT OR IGNORE INTO labels VALUES (?1, ?2)")?;
+        for t in transactions {
+            // Date format understood by SQLite date and time functions
+            let date = t.date.format("%Y-%m-%d %H:%M:%S").to_string();
+            insert_transaction.execute(params![t.id, t.account, date, t.description, t.amount as f64, t.currency])?;
+            for label in &t.labels {
+                insert_label.execute(params![t.id, label])?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+/// Convert transactions to beancount format. Each transaction is posted to 'Assets:<account>', with a counter-posting
+/// to 'Expenses:<label>' for spending or 'Income:<label>' for income, using the first label of the transaction.
+fn to_beancount(transactions: &[Transaction], default_currency: &str) -> String {
+    let mut postings = vec![];
+    let mut accounts = BTreeSet::new();
+    for t in transactions {
+        let account = beancount_account("Assets", &t.account);
+        let category = if t.amount > 0.0 { "Income" } else { "Expenses" };
+        let counter_account = beancount_account(category, t.labels.first().map(String::as_str).unwrap_or("uncategorised"));
+        let currency = t.currency.as_deref().unwrap_or(default_currency);
+        let narration = t.description.replace('"', "\\\"");
+
+        postings.push(format!("{} * \"{narration}\"\n  {account}  {:.2} {currency}\n  {counter_account}  {:.2} {currency}\n",
+                              t.date.format("%Y-%m-%d"), t.amount, -t.amount));
+        accounts.insert(account);
+        accounts.insert(counter_account);
+    }
+
+    // Beancount requires accounts to be opened before they are used
+    let mut output = String::new();
+    if let Some(first) = transactions.iter().map(|t| t.date).min() {
+        for account in accounts {
+            output.push_str(&format!("{} open {account}\n", first.format("%Y-%m-%d")));
+        }
+    }
+    for posting in postings {
+        output.push('\n');
+        output.push_str(&posting);
+    }
+    output
+}
+
+/// Beancount account name, e.g. 'Assets:Amex-gold'. Each part must start with a capital letter and
+/// contain only letters, numbers and dashes. Names not starting with a letter are prefixed with 'X', e.g. 'Expenses:X2023-trip'.
+fn beancount_account(category: &str, name: &str) -> String {
+    let name: String = name.chars().map(|c| if c.is_alphanumeric() { c } else { '-' }).collect();
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_alphabetic() => format!("{category}:{}{}", first.to_uppercase(), chars.as_str()),
+        Some(_) => format!("{category}:X{name}"),
+        None => category.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use chrono::NaiveDateTime;
+    use std::fs;
+    use crate::config::Config;
+    use rusqlite::Connection;
+    use crate::controller::export::{execute_export_db, to_beancount, to_markdown, to_sqlite};
+    use crate::csv_reader::{self, Record};
+    use crate::db::Database;
+    use crate::db::label_op::LabelCommand;
+    use crate::json_reader;
+    use crate::labeller::Labeller;
+    use crate::parser::{Condition, Operator, OrderBy};
+    use crate::transaction::Transaction;
+
+    #[test]
+    fn test_label_with_spaces_round_trip() {
+        let mut db = Database::new("test.db".to_string());
+        db.upsert(&Record::new("amex", "2023-02-21", "Joe's Cafe", -4.5));
+
+        // Label from auto labelling rules
+        let config: Config = toml::from_str("[labels]\n\"Eating Out\" = \"cafe\"").unwrap();
+        db.apply_label_ops(1, LabelCommand::Auto, &Labeller::new(&config));
+        let condition = Condition::Label(Operator::Eq, "eating out".into());
+        assert_eq!(db.query(None, Some(condition), OrderBy::date(), None).len(), 1);
+
+        for extension in ["csv", "json"] {
+            let file_path = std::env::temp_dir().join(format!("perfidb_test_labels_{}.{extension}", std::process::id()));
+            execute_export_db(&mut db, file_path.to_str().unwrap(), None, None, "");
+            let records = match extension {
+                "csv" => csv_reader::read_transactions("amex", &file_path).unwrap(),
+                _ => json_reader::read_transactions("amex", &file_path).unwrap(),
+            };
+            assert_eq!(records[0].labels, Some(vec!["eating out".to_string()]));
+            fs::remove_file(file_path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let transactions = vec![
+            Transaction::new(1, "amex".into(), NaiveDateTime::from_str("2023-02-21T00:00:00").unwrap(), "Coffee | Cake", -4.5, vec!["coffee".into(), "food".into()]),
+        ];
+
+        assert_eq!(to_markdown(&transactions, None), "\
+| ID | Account | Date | Description | Amount | Labels |
+| --- | --- | --- | --- | ---: | --- |
+| 1 | amex | 2023-02-21 | Coffee \\| Cake | -4.50 | coffee, food |
+");
+    }
+
+    #[test]
+    fn test_to_sqlite() {
+        let transactions = vec![
+            Transaction::new(1, "amex".into(), NaiveDateTime::from_str("2023-02-21T00:00:00").unwrap(), "Coffee", -4.5, vec!["coffee".into(), "food".into()]),
+            Transaction::new(2, "cba".into(), NaiveDateTime::from_str("2023-02-22T00:00:00").unwrap(), "Salary", 3000.0, vec![]),
+        ];
+        let file_path = std::env::temp_dir().join(format!("perfidb_test_export_{}.sqlite", std::process::id()));
+        let file_path = file_path.to_str().unwrap();
+
+        // Exporting twice replaces the file instead of failing on existing tables
+        to_sqlite(&transactions, file_path).unwrap();
+        to_sqlite(&transactions, file_path).unwrap();
+
+        let conn = Connection::open(file_path).unwrap();
+        let (count, total): (u32, f64) = conn.query_row("SELECT COUNT(*), SUM(amount) FROM transactions", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert_eq!((count, total), (2, 2995.5));
+
+        let (description, date): (String, String) = conn.query_row(
+            "SELECT description, date FROM transactions t JOIN labels l ON t.id = l.transaction_id WHERE l.label = 'food'",
+            [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert_eq!((description.as_str(), date.as_str()), ("Coffee", "2023-02-21 00:00:00"));
+
+        drop(conn);
+        fs::remove_file(file_path).unwrap();
+    }
+
+    #[test]
+    fn test_to_beancount() {
+        let mut salary = Transaction::new(2, "cba".into(), NaiveDateTime::from_str("2023-02-22T00:00:00").unwrap(), "Salary", 3000.0, vec!["salary".into()]);
+        salary.currency = Some("AUD".into());
+        let transactions = vec![
+            Transaction::new(1, "amex gold".into(), NaiveDateTime::from_str("2023-02-21T00:00:00").unwrap(), "Joe's \"Cafe\"", -4.5, vec!["coffee".into(), "food".into()]),
+            salary,
+            Transaction::new(3, "7eleven".into(), NaiveDateTime::from_str("2023-02-23T00:00:00").unwrap(), "Snacks", -6.0, vec!["2023 trip".into()]),
+        ];
+
+        assert_eq!(to_beancount(&transactions, "USD"), "\
+2023-02-21 open Assets:Amex-gold
+2023-02-21 open Assets:Cba
+2023-02-21 open Assets:X7eleven
+2023-02-21 open Expenses:Coffee
+2023-02-21 open Expenses:X2023-trip
+2023-02-21 open Income:Salary
+
+2023-02-21 * \"Joe's \\\"Cafe\\\"\"
+  Assets:Amex-gold  -4.50 USD
+  Expenses:Coffee  4.50 USD
+
+2023-02-22 * \"Salary\"
+  Assets:Cba  3000.00 AUD
+  Income:Salary  -3000.00 AUD
+
+2023-02-23 * \"Snacks\"
+  Assets:X7eleven  -6.00 USD
+  Expenses:X2023-trip  6.00 USD
+");
+    }
+}
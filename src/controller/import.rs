@@ -1,4 +1,4 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::ops::Neg;
 use std::path::{Path, PathBuf};
@@ -6,33 +6,59 @@ use anyhow::anyhow;
 use comfy_table::{Table, TableComponent};
 use log::{info, warn};
 use walkdir::{DirEntry, WalkDir};
+use crate::audit::AuditLog;
+use crate::config::Config;
 use crate::csv_reader;
+use crate::csv_reader::Record;
+use crate::json_reader;
 use crate::db::Database;
+use crate::db::label_op::LabelCommand;
+use crate::labeller::Labeller;
 
-/// Import transactions from a file
-pub(crate) fn execute_import(db : &mut Database, import_root_dir :&PathBuf, inverse_amount: bool, dry_run: bool) {
+/// Import transactions from a file. If `autolabel` is true, auto labelling rules are applied to the new transactions.
+/// If `account` is given, all files are imported into it instead of the account derived from their path.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn execute_import(db : &mut Database, import_root_dir :&PathBuf, inverse_amount: bool, dry_run: bool, autolabel: bool, account: Option<&str>, config_file: &str, audit_log: &AuditLog) {
     let current_dir_files = scan_files(import_root_dir).unwrap();
-    let new_files = diff_files(&db, &current_dir_files);
+    let new_files = check_new_files(db, import_root_dir, &current_dir_files);
     if new_files.is_empty() {
         info!("No new statement files detected.");
         return;
     }
 
-    for f in new_files.iter() {
-        // Derive account name from the first segment of path.
-        // E.g. for amex/2023-01.csv the account name will be 'amex'.
-        let account = match f.split_once(std::path::MAIN_SEPARATOR) {
-            None => "default",
-            Some((first_segment, _)) => first_segment
-        };
+    let config = db.config(config_file);
+    if dry_run {
+        print_new_files(import_root_dir, &new_files, account, &config);
+    }
+
+    let labeller = if autolabel && !dry_run { Some(db.labeller(config_file)) } else { None };
+    let mut imported_files = vec![];
+    for (f, status) in new_files.iter() {
+        match status {
+            FileStatus::New => {},
+            FileStatus::Duplicate(original) => {
+                info!("Skipping {f}, it has the same content as {original}");
+                continue;
+            },
+            FileStatus::Unreadable(e) => {
+                warn!("Unable to read {f}: {e}");
+                continue;
+            }
+        }
 
+        let account = account.unwrap_or_else(|| derive_account(f, &config));
         let path = PathBuf::from(import_root_dir).join(f);
-        let result = copy_from_csv(path.as_path(), db, account, inverse_amount, dry_run);
+        // 'inverse' flag in IMPORT statement takes precedence over the account's configuration
+        let inverse_amount = if inverse_amount { Some(true) } else { config.inverse_amount_of(account) };
+        let result = copy_from_csv(path.as_path(), db, account, inverse_amount, dry_run, labeller.as_deref());
         match result {
-            Ok(()) => {
+            Ok(trans_ids) => {
                 if !dry_run {
                     let md5 = md5::compute(fs::read(path).unwrap());
                     db.record_file_md5(f, md5).expect("Unable to record file md5");
+                    db.record_source_file(f, &trans_ids);
+                    audit_log.record(&format!("IMPORT {f}"), &trans_ids);
+                    imported_files.push(f.clone());
                 }
             },
             Err(e) => {
@@ -40,79 +66,114 @@ pub(crate) fn execute_import(db : &mut Database, import_root_dir :&PathBuf, inve
             }
         }
     }
-    db.save();
+    if !dry_run {
+        // An IMPORT which failed to import any file doesn't replace the batch to roll back
+        if !imported_files.is_empty() {
+            db.set_last_import(imported_files);
+        }
+        db.save();
+    }
 }
 
-fn copy_from_csv(path: &Path, db: &mut Database, table_name: &str, mut inverse_amount: bool, dry_run: bool) -> anyhow::Result<()> {
+/// Import transactions from a file. If `inverse_amount` is None, user is asked when most amounts are positive.
+/// If `labeller` is specified, it labels the new transactions which don't have labels yet.
+/// Returns ids of the imported transactions, which is empty for dry-run.
+fn copy_from_csv(path: &Path, db: &mut Database, table_name: &str, inverse_amount: Option<bool>, dry_run: bool, labeller: Option<&Labeller>) -> anyhow::Result<Vec<u32>> {
     if dry_run {
         info!("Dry run. Printing transactions from {}", path.display());
-    } else {
-        info!("Importing transactions from {}", path.display());
-    }
-
-    let result = csv_reader::read_transactions(table_name, path);
-    match result {
-        Ok(mut records) => {
-            if dry_run {
-                let mut table = Table::new();
-                table.set_header(vec!["Account", "Date", "Description", "Amount"]);
-                table.remove_style(TableComponent::HorizontalLines);
-                table.remove_style(TableComponent::MiddleIntersections);
-                table.remove_style(TableComponent::LeftBorderIntersections);
-                table.remove_style(TableComponent::RightBorderIntersections);
-                for r in &records {
-                    table.add_row(vec![r.account.as_str(), r.date.to_string().as_str(), r.description.as_str(), format!("{:.2}", r.amount).as_str()]);
-                }
-                println!("{table}");
-                info!("This is a dry-run. Transactions are not imported");
-                return Ok(());
-            }
 
-            // If inverse_amount flag is not set
-            if !inverse_amount {
-                // We should check if most transactions have positive amount. If this is the case it's likely to be
-                // inverse amount, so we should prompt user
+        // Only dry-run needs all records in memory, to print them in a table
+        let records: Vec<Record> = read_records(table_name, path)?.collect::<anyhow::Result<_>>()?;
+        let mut table = Table::new();
+        table.set_header(vec!["Account", "Date", "Description", "Amount"]);
+        table.remove_style(TableComponent::HorizontalLines);
+        table.remove_style(TableComponent::MiddleIntersections);
+        table.remove_style(TableComponent::LeftBorderIntersections);
+        table.remove_style(TableComponent::RightBorderIntersections);
+        for r in &records {
+            table.add_row(vec![r.account.as_str(), r.date.to_string().as_str(), r.description.as_str(), format!("{:.2}", r.amount).as_str()]);
+        }
+        println!("{table}");
+        info!("This is a dry-run. Transactions are not imported");
+        return Ok(vec![]);
+    }
 
-                let mut positive_amount_count = 0usize;
-                for r in records.iter() {
-                    if r.amount > 0.0 {
-                        positive_amount_count += 1;
-                    }
-                }
-                // If more than 50% of records have positive amount
-                if positive_amount_count as f32 / records.len() as f32 > 0.5 {
-                    // ask user if they want to set 'inverse_amount' flag to true
-                    println!("Most transactions in {} have positive amount value.\n\
-                    Do you want to set 'inverse_amount' flag so positive amount are treated as spending and \
-                    negative are treated as income?\n\
-                    yes or no, default is 'yes': ", path.display());
-
-                    let mut user_input = String::new();
-                    std::io::stdin().read_line(&mut user_input).unwrap();
-                    let user_input = user_input.trim().to_lowercase();
-                    if user_input.is_empty() || user_input == "yes" {
-                        inverse_amount = true;
-                    }
-                }
+    info!("Importing transactions from {}", path.display());
 
-                if inverse_amount {
-                    for r in records.iter_mut() {
-                        r.amount = r.amount.neg();
-                    }
-                }
+    // If inverse_amount is neither set in IMPORT statement nor configured for the account
+    let inverse_amount = match inverse_amount {
+        Some(inverse_amount) => inverse_amount,
+        None => ask_inverse_amount(table_name, path)?
+    };
 
-                for r in &records {
-                    db.upsert(r);
-                }
-                db.save();
-                println!("Imported {} transactions", &records.len());
+    // Check every row can be parsed first, so an invalid row doesn't leave the file partially imported
+    for r in read_records(table_name, path)? {
+        r?;
+    }
+
+    // Read the file again and upsert records as they are parsed, so memory stays flat for large files
+    let mut trans_ids = vec![];
+    for r in read_records(table_name, path)? {
+        let mut r = r?;
+        if inverse_amount {
+            r.raw_amount = Some(r.amount);
+            r.amount = r.amount.neg();
+            r.balance = r.balance.map(f32::neg);
+        }
+        if let Some(trans_id) = db.upsert(&r) {
+            if let (Some(labeller), None) = (labeller, &r.labels) {
+                db.apply_label_ops(trans_id, LabelCommand::Auto, labeller);
             }
-            Ok(())
-        },
-        Err(e) => {
-            Err(anyhow!(e))
+            trans_ids.push(trans_id);
         }
     }
+    db.save();
+    println!("Imported {} transactions", trans_ids.len());
+    Ok(trans_ids)
+}
+
+/// Check if most transactions in the file have positive amount. If this is the case it's likely to be
+/// inverse amount, so we should ask user whether to inverse amounts.
+fn ask_inverse_amount(table_name: &str, path: &Path) -> anyhow::Result<bool> {
+    let mut positive_amount_count = 0usize;
+    let mut records_count = 0usize;
+    for r in read_records(table_name, path)? {
+        let r = r?;
+        records_count += 1;
+        if r.amount > 0.0 {
+            positive_amount_count += 1;
+        }
+    }
+    // If more than 50% of records have positive amount
+    if positive_amount_count as f32 / records_count as f32 > 0.5 {
+        // ask user if they want to set 'inverse_amount' flag to true
+        println!("Most transactions in {} have positive amount value.\n\
+        Do you want to set 'inverse_amount' flag so positive amount are treated as spending and \
+        negative are treated as income?\n\
+        yes or no, default is 'yes': ", path.display());
+
+        let mut user_input = String::new();
+        std::io::stdin().read_line(&mut user_input).unwrap();
+        let user_input = user_input.trim().to_lowercase();
+        if user_input.is_empty() || user_input == "yes" {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Read transactions from a csv or json file. Csv files are read lazily.
+fn read_records(table_name: &str, path: &Path) -> anyhow::Result<Box<dyn Iterator<Item = anyhow::Result<Record>>>> {
+    if is_json(path) {
+        Ok(Box::new(json_reader::read_transactions(table_name, path)?.into_iter().map(Ok)))
+    } else {
+        Ok(Box::new(csv_reader::stream_transactions(table_name, path).map_err(|e| anyhow!(e))?.map(|r| r.map_err(|e| anyhow!(e)))))
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().map(|ext| ext == "json").unwrap_or(false)
 }
 
 /// Scan a dir recursively and list all eligible bank statement files
@@ -121,43 +182,99 @@ pub(crate) fn scan_files(root_path: &PathBuf) -> anyhow::Result<BTreeSet<String>
     
     let mut files = BTreeSet::new();
     let walker = WalkDir::new(root_path).into_iter();
-    for entry in walker.filter_entry(|e| !is_hidden(e)) {
-        if let Ok(dir_entry) = entry {
-            // Ignore symlinks
-            if dir_entry.path_is_symlink() {
-                continue;
-            }
+    for dir_entry in walker.filter_entry(|e| !is_hidden(e)).flatten() {
+        // Ignore symlinks
+        if dir_entry.path_is_symlink() {
+            continue;
+        }
 
-            let path = dir_entry.path();
-            // Ignore directory
-            if path.is_dir() {
-                continue;
-            }
+        let path = dir_entry.path();
+        // Ignore directory
+        if path.is_dir() {
+            continue;
+        }
 
-            let canonical = path.canonicalize()?;
-            // file_id is the sub path from the importing root dir.
-            // E.g. if importing from /Users/ren/bank-statements, the file /Users/ren/bank-statements/amex/2023-01.csv
-            // will have the file id 'amex/2023-01.csv'
-            let file_id = canonical.strip_prefix(&root_path)?.to_str().unwrap();
-            if file_id.ends_with(".csv") {
-                files.insert(file_id.into());
-            }
+        let canonical = path.canonicalize()?;
+        // file_id is the sub path from the importing root dir.
+        // E.g. if importing from /Users/ren/bank-statements, the file /Users/ren/bank-statements/amex/2023-01.csv
+        // will have the file id 'amex/2023-01.csv'
+        let file_id = canonical.strip_prefix(root_path)?.to_str().unwrap();
+        if file_id.ends_with(".csv") || file_id.ends_with(".tsv") || file_id.ends_with(".json") {
+            files.insert(file_id.into());
         }
     }
 
     Ok(files)
 }
 
-/// Return a list of files that's in the new list but not in the current list
-pub(crate) fn diff_files(db: &Database, new: &BTreeSet<String>) -> BTreeSet<String> {
-    let mut diff = BTreeSet::new();
+/// Whether a file not imported before should be imported
+#[derive(Debug, PartialEq)]
+enum FileStatus {
+    New,
+    /// Same content as the given file, e.g. a renamed statement file
+    Duplicate(String),
+    Unreadable(String),
+}
+
+/// Return files that are in the new list but not imported yet, and whether each of them should be imported.
+fn check_new_files(db: &Database, import_root_dir: &Path, new: &BTreeSet<String>) -> Vec<(String, FileStatus)> {
+    let mut new_files = vec![];
+    // md5 of new files in this scan, so a file copied to two paths is only imported once
+    let mut new_md5s: HashMap<[u8; 16], &String> = HashMap::new();
     for f in new {
-        if !db.file_exist(f) {
-            diff.insert(f.into());
+        if db.file_exist(f) {
+            continue;
         }
+
+        let status = match fs::read(import_root_dir.join(f)) {
+            Ok(content) => {
+                let md5 = md5::compute(content);
+                match db.file_with_md5(&md5).or(new_md5s.get(&md5.0).copied()) {
+                    Some(original) => FileStatus::Duplicate(original.clone()),
+                    None => {
+                        new_md5s.insert(md5.0, f);
+                        FileStatus::New
+                    }
+                }
+            },
+            Err(e) => FileStatus::Unreadable(e.to_string())
+        };
+        new_files.push((f.clone(), status));
     }
 
-    diff
+    new_files
+}
+
+/// Derive account name from the first segment of path.
+/// E.g. for amex/2023-01.csv the account name will be 'amex'.
+/// Files in the import root dir use `default_account` of the config, or 'default' if not set.
+fn derive_account<'a>(file_id: &'a str, config: &'a Config) -> &'a str {
+    match file_id.split_once(std::path::MAIN_SEPARATOR) {
+        None => config.default_account.as_deref().unwrap_or("default"),
+        Some((first_segment, _)) => first_segment
+    }
+}
+
+/// Print a summary of new files found in a dry-run, so folder structure can be verified before importing
+fn print_new_files(import_root_dir: &Path, new_files: &[(String, FileStatus)], account_override: Option<&str>, config: &Config) {
+    let mut table = Table::new();
+    table.set_header(vec!["File", "Account", "Rows", "Status"]);
+    for (f, status) in new_files {
+        let account = account_override.unwrap_or_else(|| derive_account(f, config));
+        let rows = match status {
+            FileStatus::New => read_records(account, &import_root_dir.join(f))
+                .map(|records| records.count().to_string())
+                .unwrap_or_else(|e| e.to_string()),
+            _ => "".to_string()
+        };
+        let status = match status {
+            FileStatus::New => "import".to_string(),
+            FileStatus::Duplicate(original) => format!("skip, same content as {original}"),
+            FileStatus::Unreadable(e) => format!("skip, {e}"),
+        };
+        table.add_row(vec![f.as_str(), account, rows.as_str(), status.as_str()]);
+    }
+    println!("{table}");
 }
 
 fn is_hidden(entry: &DirEntry) -> bool {
@@ -166,3 +283,54 @@ fn is_hidden(entry: &DirEntry) -> bool {
         .map(|s| s.starts_with("."))
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use crate::controller::import::{check_new_files, copy_from_csv, FileStatus};
+    use crate::csv_reader::tests::fixture_filename;
+    use crate::db::Database;
+
+    #[test]
+    fn test_copy_inverse_amount() {
+        // Importing saves the database, which is not needed here
+        let mut db = Database::new("test.db".to_string());
+        db.disable_autosave();
+        let trans_ids = copy_from_csv(&fixture_filename("balance.csv"), &mut db, "cba", Some(true), false, None).unwrap();
+        assert_eq!(trans_ids, vec![1, 2]);
+
+        // Amount as in the statement is kept, so the sign can be checked
+        let t = db.search_by_id(1).unwrap();
+        assert_eq!((t.amount, t.raw_amount, t.balance), (50.0, Some(-50.0), Some(-1950.0)));
+
+        let mut db = Database::new("test.db".to_string());
+        db.disable_autosave();
+        copy_from_csv(&fixture_filename("balance.csv"), &mut db, "cba", Some(false), false, None).unwrap();
+        let t = db.search_by_id(1).unwrap();
+        assert_eq!((t.amount, t.raw_amount), (-50.0, None));
+    }
+
+    #[test]
+    fn test_check_new_files() {
+        let root = std::env::temp_dir().join(format!("perfidb_import_{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("2023-01.csv"), "2023-01-05,-50,Woolworths").unwrap();
+        fs::write(root.join("jan.csv"), "2023-01-05,-50,Woolworths").unwrap();
+        fs::write(root.join("copy-of-feb.csv"), "2023-02-05,-80,Coles").unwrap();
+        fs::write(root.join("feb.csv"), "2023-02-05,-80,Coles").unwrap();
+
+        let mut db = Database::new("test.db".to_string());
+        db.record_file_md5("2023-01.csv", md5::compute(fs::read(root.join("2023-01.csv")).unwrap())).unwrap();
+
+        let files: BTreeSet<String> = ["2023-01.csv", "jan.csv", "copy-of-feb.csv", "feb.csv"].iter().map(|f| f.to_string()).collect();
+        let new_files = check_new_files(&db, &root, &files);
+        assert_eq!(new_files, vec![
+            ("copy-of-feb.csv".to_string(), FileStatus::New),
+            ("feb.csv".to_string(), FileStatus::Duplicate("copy-of-feb.csv".to_string())),
+            ("jan.csv".to_string(), FileStatus::Duplicate("2023-01.csv".to_string())),
+        ]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use comfy_table::{Cell, CellAlignment, Table, TableComponent};
+use crate::controller::select::format_amount;
+use crate::db::Database;
+use crate::parser::{Operator, OrderBy};
+use crate::transaction::Transaction;
+
+/// Print merchants whose total spending in a month satisfies `operator threshold`, e.g. `> 100`
+pub(crate) fn execute_merchants(db: &mut Database, operator: Operator, threshold: f32) {
+    let transactions = db.query(None, None, OrderBy::date(), None);
+
+    let mut table = Table::new();
+    table.remove_style(TableComponent::HorizontalLines);
+    table.remove_style(TableComponent::MiddleIntersections);
+    table.remove_style(TableComponent::LeftBorderIntersections);
+    table.remove_style(TableComponent::RightBorderIntersections);
+    table.set_header(vec!["Merchant", "Month", "Spending"]);
+    for (merchant, month, total) in monthly_merchant_spending(&transactions, operator, threshold) {
+        table.add_row(vec![
+            Cell::new(merchant),
+            Cell::new(month),
+            Cell::new(format_amount(total)).set_alignment(CellAlignment::Right),
+        ]);
+    }
+
+    println!("{table}");
+}
+
+/// Total spending of each merchant in each month, keeping only those satisfying `operator threshold`.
+/// Sorted by month, then by spending with the largest first.
+fn monthly_merchant_spending(transactions: &[Transaction], operator: Operator, threshold: f32) -> Vec<(String, String, f32)> {
+    let mut totals: BTreeMap<(String, String), f32> = BTreeMap::new();
+    // Spending has negative amount
+    for t in transactions.iter().filter(|t| t.amount < 0.0) {
+        *totals.entry((t.date.format("%Y-%m").to_string(), merchant_of(&t.description))).or_insert(0.0) -= t.amount;
+    }
+
+    let mut results: Vec<(String, String, f32)> = totals.into_iter()
+        .filter(|(_, total)| match operator {
+            Operator::Eq => *total == threshold,
+            Operator::NotEq => *total != threshold,
+            Operator::Gt => *total > threshold,
+            Operator::GtEq => *total >= threshold,
+            Operator::Lt => *total < threshold,
+            Operator::LtEq => *total <= threshold,
+            _ => false
+        })
+        .map(|((month, merchant), total)| (merchant, month, total))
+        .collect();
+    results.sort_by(|a, b| a.1.cmp(&b.1).then(b.2.total_cmp(&a.2)));
+    results
+}
+
+/// Normalise a description into a merchant name, dropping tokens with digits such as store numbers and
+/// references, e.g. 'WOOLWORTHS 1234 SYDNEY' and 'Woolworths 5678 Sydney' are both 'woolworths sydney'.
+fn merchant_of(description: &str) -> String {
+    let tokens: Vec<String> = description.split_whitespace()
+        .filter(|token| !token.chars().any(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+        .collect();
+    if tokens.is_empty() {
+        description.to_lowercase()
+    } else {
+        tokens.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use chrono::NaiveDateTime;
+    use crate::controller::merchants::{merchant_of, monthly_merchant_spending};
+    use crate::parser::Operator;
+    use crate::transaction::Transaction;
+
+    #[test]
+    fn test_monthly_merchant_spending() {
+        assert_eq!(merchant_of("WOOLWORTHS 1234 SYDNEY"), "woolworths sydney");
+
+        let date = |s: &str| NaiveDateTime::from_str(&format!("{s}T00:00:00")).unwrap();
+        let transactions = vec![
+            Transaction::new(1, "cba".into(), date("2023-01-05"), "WOOLWORTHS 1234 SYDNEY", -60.0, vec![]),
+            Transaction::new(2, "cba".into(), date("2023-01-20"), "Woolworths 5678 Sydney", -70.0, vec![]),
+            Transaction::new(3, "cba".into(), date("2023-01-21"), "Uber", -150.0, vec![]),
+            Transaction::new(4, "cba".into(), date("2023-01-22"), "Salary", 3000.0, vec![]),
+            Transaction::new(5, "cba".into(), date("2023-02-05"), "WOOLWORTHS 1234 SYDNEY", -60.0, vec![]),
+        ];
+
+        assert_eq!(monthly_merchant_spending(&transactions, Operator::Gt, 100.0), vec![
+            ("uber".to_string(), "2023-01".to_string(), 150.0),
+            ("woolworths sydney".to_string(), "2023-01".to_string(), 130.0),
+        ]);
+    }
+}
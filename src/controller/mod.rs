@@ -1,72 +1,176 @@
 use std::path::PathBuf;
-use log::info;
-use crate::config::Config;
+use log::{info, warn};
+use crate::audit::AuditLog;
 use crate::db::Database;
-use crate::db::label_op::LabelCommand;
-use crate::labeller::Labeller;
 use crate::parser;
 use crate::parser::{OrderBy, Projection};
-use crate::parser::Statement::{AutoLabel, Delete, Export, Import, Insert, Label, Select};
+use crate::parser::Statement::{AutoLabel, Delete, Export, Import, Insert, Label, Log, Select, Vacuum, Budget, Similar, Suggest, MergeLabel, Move, Replay, Save, Reconcile, ReloadRules, Merchants, CreateView, View, Diff, ListAccounts, RollbackLastImport};
 
 mod export;
 mod select;
 mod insert;
+mod delete;
 mod import;
+mod budget;
+mod similar;
+mod reconcile;
+mod merchants;
+mod diff;
+mod accounts;
+mod auto_label;
 
-pub(crate) fn parse_and_run_command(db: &mut Database, import_root_dir: &PathBuf, sql: String, auto_label_rules_file: &str) -> Result<(), String> {
+pub(crate) fn parse_and_run_command(db: &mut Database, import_root_dir: Option<&PathBuf>, sql: String, auto_label_rules_file: &str, audit_log: &AuditLog) -> Result<(), String> {
     // First use our own parser to parse
     let result = parser::parse(&sql);
 
     match result {
         Ok((_input, statement)) => {
+            if db.is_readonly() && statement.is_mutation() {
+                warn!("Database is read-only, the statement is ignored.");
+                return Ok(());
+            }
+
             match statement {
-                Export(file_path) => {
-                    export::execute_export_db(db, &file_path);
+                Export(file_path, condition, group_by) => {
+                    export::execute_export_db(db, &file_path, condition, group_by, auto_label_rules_file);
                 }
-                Import(inverse_amount, dryrun) => {
-                    import::execute_import(db, import_root_dir, inverse_amount, dryrun);
+                Import(inverse_amount, dryrun, autolabel, account) => {
+                    match import_root_dir {
+                        Some(import_root_dir) => import::execute_import(db, import_root_dir, inverse_amount, dryrun, autolabel, account.as_deref(), auto_label_rules_file, audit_log),
+                        None => warn!("No import root dir. Specify it with --import-root-dir, or set 'import_root_dir' in {auto_label_rules_file}")
+                    }
                 }
                 Select(projection, from, condition, order_by, limit, group_by) => {
+                    db.last_select = Some(sql.clone());
                     select::run_select(db, projection, from, condition, order_by, limit, group_by, auto_label_rules_file);
                 }
                 Label(trans_ids, label_cmd) => {
-                    for trans_id in trans_ids {
+                    let labeller = db.labeller(auto_label_rules_file);
+                    for trans_id in &trans_ids {
                         // TODO: avoid copying vec multiple times
-                        db.apply_label_ops(trans_id, label_cmd.clone(), auto_label_rules_file)
+                        db.apply_label_ops(*trans_id, label_cmd.clone(), &labeller)
                     }
+                    db.save();
+                    audit_log.record(&sql, &trans_ids);
                     info!("\nLabel operations completed.")
                 }
-                AutoLabel(condition, is_run) => {
+                AutoLabel(condition, is_run, label_cmd) => {
+                    let labeller = db.labeller(auto_label_rules_file);
+                    let config = db.config(auto_label_rules_file);
+                    let transactions = auto_label::execute_auto_label(db, condition, is_run, label_cmd, &labeller);
                     if is_run {
-                        let transactions = db.query(None, Some(condition.clone()), OrderBy::date(), None);
-                        for t in transactions {
-                            db.apply_label_ops(t.id, LabelCommand::Auto, auto_label_rules_file);
-                        }
-                        let transactions = db.query(None, Some(condition), OrderBy::date(), None);                       
-                        select::process_projection(&Projection::Auto, None, &transactions);
-                    } else {
-                        let mut transactions = db.query(None, Some(condition), OrderBy::date(), None);
-                        let tagger = Labeller::new(&Config::load_from_file(auto_label_rules_file));
-                        for t in transactions.iter_mut() {
-                            let new_labels = tagger.label(&t.description);
-                            t.labels = new_labels;
-                        }
-                        select::process_projection(&Projection::Auto, None, &transactions);
+                        audit_log.record(&sql, &transactions.iter().map(|t| t.id).collect::<Vec<u32>>());
                     }
+                    select::process_projection(&Projection::Auto, None, None, &config, transactions);
                 },
                 Insert(account, records) => {
-                    let records_count = insert::execute_insert(db, account, records);
-                    info!("\n{records_count} transactions inserted.");
+                    let trans_ids = insert::execute_insert(db, account, records);
+                    audit_log.record(&sql, &trans_ids);
+                    info!("\n{} transactions inserted.", trans_ids.len());
                 }
-                Delete(trans_ids) => {
+                Delete(trans_ids, force) => {
                     match trans_ids {
-                        Some(trans_ids) => {
-                            let trans_deleted = db.delete(&trans_ids);
-                            info!("{trans_deleted} transactions deleted.");
+                        Some(trans_ids) => match delete::execute_delete(db, &trans_ids, force) {
+                            Some(deleted) => {
+                                if !deleted.is_empty() {
+                                    audit_log.record(&sql, &deleted);
+                                }
+                                info!("{} transactions deleted.", deleted.len());
+                            },
+                            None => info!("Delete cancelled.")
                         },
                         None => info!("Unable to parse transaction IDs to delete, ignore operation.")
                     }
                 }
+                RollbackLastImport => {
+                    let (files, trans_ids) = db.rollback_last_import();
+                    if files.is_empty() {
+                        info!("No import to roll back.");
+                    } else {
+                        db.save();
+                        audit_log.record(&sql, &trans_ids);
+                        info!("{} transactions deleted, these files can be imported again: {}", trans_ids.len(), files.join(", "));
+                    }
+                }
+                Similar(trans_id, limit) => {
+                    similar::execute_similar(db, trans_id, limit);
+                }
+                MergeLabel(sources, target) => {
+                    let trans_ids = db.merge_labels(&sources, &target);
+                    db.save();
+                    audit_log.record(&sql, &trans_ids);
+                    info!("Labels merged into '{target}' on {} transactions.", trans_ids.len());
+                }
+                Move(trans_ids, condition, account) => {
+                    let trans_ids = match condition {
+                        Some(condition) => db.query(None, Some(condition), OrderBy::date(), None).iter().map(|t| t.id).collect(),
+                        None => trans_ids
+                    };
+                    let moved = db.move_to_account(&trans_ids, &account);
+                    db.save();
+                    audit_log.record(&sql, &moved);
+                    info!("{} transactions moved to '{account}'.", moved.len());
+                }
+                Suggest(trans_id) => {
+                    similar::execute_suggest(db, trans_id);
+                }
+                Budget(month) => {
+                    budget::execute_budget(db, month, auto_label_rules_file);
+                }
+                Vacuum => {
+                    let stats = db.compact();
+                    db.save();
+                    info!("Removed {} empty index entries, reclaimed {} bytes.", stats.entries_removed, stats.bytes_reclaimed);
+                }
+                ReloadRules => {
+                    db.reload_rules(auto_label_rules_file);
+                    info!("Auto labelling rules and config reloaded from {auto_label_rules_file}");
+                }
+                Diff(file_path) => {
+                    diff::execute_diff(db, &file_path);
+                }
+                ListAccounts => {
+                    accounts::execute_accounts(db);
+                }
+                Merchants(operator, threshold) => {
+                    merchants::execute_merchants(db, operator, threshold);
+                }
+                Reconcile => {
+                    reconcile::execute_reconcile(db);
+                }
+                Save => {
+                    db.flush();
+                    info!("Database saved.");
+                }
+                CreateView(name, view_sql) => {
+                    db.create_view(&name, &view_sql);
+                    db.save();
+                    audit_log.record(&sql, &[]);
+                    info!("View '{name}' created.");
+                }
+                View(name) => {
+                    match db.view(&name).cloned() {
+                        Some(view_sql) => {
+                            info!("{view_sql}");
+                            return parse_and_run_command(db, import_root_dir, view_sql, auto_label_rules_file, audit_log);
+                        },
+                        None => warn!("View '{name}' does not exist.")
+                    }
+                }
+                Replay => {
+                    match db.last_select.clone() {
+                        Some(last_select) => {
+                            info!("{last_select}");
+                            return parse_and_run_command(db, import_root_dir, last_select, auto_label_rules_file, audit_log);
+                        },
+                        None => info!("No SELECT statement to replay.")
+                    }
+                }
+                Log(count) => {
+                    for entry in audit_log.tail(count.unwrap_or(20)) {
+                        println!("{entry}");
+                    }
+                }
             }
         },
         Err(e) => {
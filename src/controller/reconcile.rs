@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use comfy_table::{Cell, CellAlignment, Color, Table, TableComponent};
+use log::info;
+use crate::controller::select::format_amount;
+use crate::db::Database;
+use crate::parser::OrderBy;
+use crate::transaction::Transaction;
+
+/// A recorded balance which does not match the previous balance plus amounts of transactions in between
+#[derive(Debug, PartialEq)]
+struct Discrepancy {
+    trans_id: u32,
+    recorded: f32,
+    expected: f32,
+}
+
+/// Result of reconciling balances of an account
+#[derive(Debug, PartialEq)]
+struct Reconciliation {
+    account: String,
+    balances_checked: usize,
+    discrepancy: Option<Discrepancy>,
+}
+
+/// Check recorded balances of each account are consistent with amounts of its transactions, to catch
+/// missing or duplicated transactions.
+pub(crate) fn execute_reconcile(db: &mut Database) {
+    let transactions = db.query(None, None, OrderBy::date(), None);
+    let reconciliations = reconcile(&transactions);
+    if reconciliations.is_empty() {
+        info!("No balances recorded. Balances are imported from statements with a 'balance' column.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table.remove_style(TableComponent::HorizontalLines);
+    table.remove_style(TableComponent::MiddleIntersections);
+    table.remove_style(TableComponent::LeftBorderIntersections);
+    table.remove_style(TableComponent::RightBorderIntersections);
+    table.set_header(vec!["Account", "Balances checked", "Result"]);
+    for r in reconciliations {
+        let result = match r.discrepancy {
+            None => Cell::new("OK").fg(Color::Green),
+            Some(d) => Cell::new(format!("Transaction {} has balance {}, expected {}",
+                                         d.trans_id, format_amount(d.recorded), format_amount(d.expected))).fg(Color::Red),
+        };
+        table.add_row(vec![
+            Cell::new(r.account),
+            Cell::new(r.balances_checked).set_alignment(CellAlignment::Right),
+            result,
+        ]);
+    }
+    println!("{table}");
+}
+
+/// Reconcile balances of each account that has any. Transactions are checked in date order. Statements can list
+/// transactions of a day in either order, so transactions of the same day are also checked in reverse order.
+fn reconcile(transactions: &[Transaction]) -> Vec<Reconciliation> {
+    let mut accounts: BTreeMap<&str, Vec<&Transaction>> = BTreeMap::new();
+    for t in transactions {
+        accounts.entry(t.account.as_str()).or_default().push(t);
+    }
+
+    let mut reconciliations = vec![];
+    for (account, mut transactions) in accounts {
+        if transactions.iter().all(|t| t.balance.is_none()) {
+            continue;
+        }
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then(a.id.cmp(&b.id)));
+
+        let mut balance = None;
+        let mut balances_checked = 0;
+        let mut discrepancy = None;
+        for day in transactions.chunk_by(|a, b| a.date.date() == b.date.date()) {
+            let result = check_balances(balance, day.iter().copied())
+                .or_else(|e| check_balances(balance, day.iter().rev().copied()).map_err(|_| e));
+            match result {
+                Ok((day_balance, checked)) => {
+                    balance = day_balance;
+                    balances_checked += checked;
+                },
+                Err(e) => {
+                    discrepancy = Some(e);
+                    break;
+                }
+            }
+        }
+
+        reconciliations.push(Reconciliation { account: account.to_string(), balances_checked, discrepancy });
+    }
+    reconciliations
+}
+
+/// Check each recorded balance equals the previous balance plus the transaction amount.
+/// Returns the balance after the last transaction and the number of balances checked.
+fn check_balances<'a>(mut balance: Option<f64>, transactions: impl Iterator<Item = &'a Transaction>) -> Result<(Option<f64>, usize), Discrepancy> {
+    let mut checked = 0;
+    for t in transactions {
+        let expected = balance.map(|balance| balance + t.amount as f64);
+        balance = match (t.balance, expected) {
+            (Some(recorded), Some(expected)) => {
+                if to_cents(recorded as f64) != to_cents(expected) {
+                    return Err(Discrepancy { trans_id: t.id, recorded, expected: expected as f32 });
+                }
+                checked += 1;
+                Some(recorded as f64)
+            },
+            (Some(recorded), None) => Some(recorded as f64),
+            (None, expected) => expected,
+        };
+    }
+    Ok((balance, checked))
+}
+
+fn to_cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use chrono::NaiveDateTime;
+    use crate::controller::reconcile::{Discrepancy, reconcile, Reconciliation};
+    use crate::transaction::Transaction;
+
+    fn transaction(id: u32, account: &str, date: &str, amount: f32, balance: Option<f32>) -> Transaction {
+        let mut t = Transaction::new(id, account.into(), NaiveDateTime::from_str(&format!("{date}T00:00:00")).unwrap(), "test", amount, vec![]);
+        t.balance = balance;
+        t
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let transactions = vec![
+            transaction(1, "cba", "2023-01-01", -10.0, Some(90.0)),
+            // Statement lists transactions of a day from the latest
+            transaction(2, "cba", "2023-01-02", -5.5, Some(64.5)),
+            transaction(3, "cba", "2023-01-02", -20.0, Some(70.0)),
+            transaction(4, "cba", "2023-01-03", 1000.0, None),
+            transaction(5, "cba", "2023-01-04", -0.1, Some(1064.4)),
+            transaction(6, "amex", "2023-01-01", -50.0, Some(-50.0)),
+            // A transaction between 6 and 7 is missing
+            transaction(7, "amex", "2023-01-05", -30.0, Some(-110.0)),
+            transaction(8, "westpac", "2023-01-05", -30.0, None),
+        ];
+
+        assert_eq!(reconcile(&transactions), vec![
+            Reconciliation { account: "amex".into(), balances_checked: 0, discrepancy: Some(Discrepancy { trans_id: 7, recorded: -110.0, expected: -80.0 }) },
+            Reconciliation { account: "cba".into(), balances_checked: 3, discrepancy: None },
+        ]);
+    }
+}
@@ -1,22 +1,72 @@
-use std::collections::HashMap;
-use chrono::NaiveDateTime;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{stdout, IsTerminal, Write};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
 use comfy_table::{Cell, CellAlignment, Color, Table, TableComponent};
+use crossterm::{execute, terminal};
+use crossterm::cursor::MoveToColumn;
+use crossterm::event::{read, Event, KeyCode};
+use crossterm::terminal::ClearType;
 use crate::config::Config;
 use crate::db::Database;
-use crate::labeller::Labeller;
-use crate::parser::{Condition, GroupBy, OrderBy, Projection};
+use crate::parser::{Condition, Field, GroupBy, GroupOrder, LabelAmount, OrderBy, Projection};
 use crate::transaction::Transaction;
+use crate::util::{colored_labels, fit_terminal_width, truncate_with_ellipsis};
+
+/// Approximate width of the columns other than description in `SELECT *` results, including borders. It is smaller
+/// than in live edit as the table shrinks columns to fit their content instead of padding them to fixed widths.
+const OTHER_COLUMNS_WIDTH: usize = 70;
+
+/// Result of a SELECT statement, before it is printed
+#[derive(Debug, PartialEq)]
+pub(crate) enum SelectResult {
+    /// SELECT *, SELECT 123, SELECT auto()
+    Transactions(Vec<Transaction>),
+    /// SELECT SUM(*)
+    Sum(f32),
+    /// SELECT COUNT(*)
+    Count(usize),
+    /// SELECT COUNT(DISTINCT field)
+    CountDistinct(Field, usize),
+    /// SELECT NET, total income and total spending
+    Net(f32, f32),
+    /// GROUP BY, header of the group column and total amount of each group in display order
+    Groups(&'static str, Vec<(String, f32)>),
+}
 
 /// Run an `SELECT` select
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn run_select(
     db: &mut Database,
     projection: Projection,
-    from: Option<String>,
+    from: Option<Vec<String>>,
     condition: Option<Condition>,
     order_by: OrderBy,
     limit: Option<usize>,
     group_by: Option<GroupBy>,
     auto_label_rules_file: &str) {
+    let config = db.config(auto_label_rules_file);
+    let is_auto_labelling = projection == Projection::Auto;
+    let result = execute_select(db, projection, from, condition, order_by, limit, group_by, &config, auto_label_rules_file);
+    render(&result, &config, is_auto_labelling);
+    if let SelectResult::Transactions(transactions) = &result {
+        if !config.hide_select_footer {
+            println!("{}", footer(transactions));
+        }
+    }
+}
+
+/// Run an `SELECT` statement and return its result without printing it
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn execute_select(
+    db: &mut Database,
+    projection: Projection,
+    from: Option<Vec<String>>,
+    condition: Option<Condition>,
+    order_by: OrderBy,
+    limit: Option<usize>,
+    group_by: Option<GroupBy>,
+    config: &Config,
+    auto_label_rules_file: &str) -> SelectResult {
     let mut transactions = match projection {
         // If select by transaction id, no need to run query, simply fetch the transaction
         Projection::Id(trans_id) => match db.search_by_id(trans_id) {
@@ -24,109 +74,321 @@ pub(crate) fn run_select(
             None => vec![]
         }
 
-        // Run query
-        _ => db.query(from, condition, order_by, limit)
+        // Run query. With GROUP BY, LIMIT applies to groups instead of transactions
+        _ => db.query(from, condition, order_by, if group_by.is_some() { None } else { limit })
     };
 
     if let Projection::Auto = projection {
-        let tagger = Labeller::new(&Config::load_from_file(auto_label_rules_file));
+        let tagger = db.labeller(auto_label_rules_file);
         for t in transactions.iter_mut() {
             let new_labels = tagger.label(&t.description);
             t.labels = new_labels;
         }
     }
 
-    process_projection(&projection, group_by, &transactions)
+    project(&projection, group_by, limit, config, transactions)
+}
+
+/// Number of transactions and their total amount, printed below the listed transactions
+fn footer(transactions: &[Transaction]) -> String {
+    // Not using sum(), which starts from -0.0 and prints 'total -0.00' for no transactions
+    let total = transactions.iter().fold(0.0, |total, t| total + t.amount);
+    let noun = if transactions.len() == 1 { "transaction" } else { "transactions" };
+    format!("{} {noun}, total {}", transactions.len(), format_amount(total))
+}
+
+/// Print outputs based on select projection, e.g. SELECT *, SELECT SUM(*), etc.
+/// `limit` is the max number of groups to print if `group_by` is specified.
+pub(crate) fn process_projection(projection: &Projection, group_by: Option<GroupBy>, limit: Option<usize>, config: &Config, transactions: Vec<Transaction>) {
+    let is_auto_labelling = *projection == Projection::Auto;
+    render(&project(projection, group_by, limit, config, transactions), config, is_auto_labelling);
+}
+
+/// Result of select projection on the selected transactions, e.g. SELECT *, SELECT SUM(*), etc.
+/// `limit` is the max number of groups if `group_by` is specified.
+fn project(projection: &Projection, group_by: Option<GroupBy>, limit: Option<usize>, config: &Config, transactions: Vec<Transaction>) -> SelectResult {
+    if let Some(group_by) = group_by {
+        let (header, totals) = group_totals(&transactions, group_by, limit, config.iso_months);
+        return SelectResult::Groups(header, totals);
+    }
+
+    match projection {
+        // SELECT * FROM ...
+        // SELECT 123 FROM ...
+        Projection::Star | Projection::Id(_) | Projection::Auto => SelectResult::Transactions(transactions),
+        Projection::Sum => SelectResult::Sum(transactions.iter().map(|t| t.amount).fold(0.0, |total, amount| total + amount)),
+        Projection::Turnover => SelectResult::Sum(transactions.iter().map(|t| t.amount.abs()).fold(0.0, |total, amount| total + amount)),
+        Projection::Count => SelectResult::Count(transactions.len()),
+        Projection::CountDistinct(field) => {
+            let values: HashSet<&str> = match field {
+                Field::Description => transactions.iter().map(|t| t.description.as_str()).collect(),
+                Field::Account => transactions.iter().map(|t| t.account.as_str()).collect(),
+                Field::Label => transactions.iter().flat_map(|t| t.labels.iter().map(String::as_str)).collect(),
+            };
+            SelectResult::CountDistinct(field.clone(), values.len())
+        },
+        Projection::Net => {
+            // Not using sum(), which starts from -0.0 and prints '-0.00' for no transactions, same as `footer`
+            let income = transactions.iter().filter(|t| t.amount > 0.0).fold(0.0, |total, t| total + t.amount);
+            let spending = transactions.iter().filter(|t| t.amount < 0.0).fold(0.0, |total, t| total + t.amount);
+            SelectResult::Net(income, spending)
+        },
+    }
 }
 
-/// Print outputs based on select projection, e.g. SELECT *, SELECT SUM(*), etc
-pub(crate) fn process_projection(projection: &Projection, group_by: Option<GroupBy>, transactions: &[Transaction]) {
+/// Header of the group column, and total amount of each group in display order.
+/// If `limit` is specified, only groups with the largest amounts are kept.
+fn group_totals(transactions: &[Transaction], group_by: GroupBy, limit: Option<usize>, iso_months: bool) -> (&'static str, Vec<(String, f32)>) {
+    match group_by {
+        GroupBy::Label(label_amount, order) => {
+            let mut totals = label_totals(transactions, label_amount);
+            sort_groups(&mut totals, limit, order);
+            let header = if label_amount == LabelAmount::Primary { "Primary tag" } else { "Tag" };
+            (header, totals.into_iter().map(|(label, total)| (label.to_string(), total)).collect())
+        },
+        GroupBy::Account(order) => {
+            let mut totals = account_totals(transactions);
+            sort_groups(&mut totals, limit, order);
+            ("Account", totals.into_iter().map(|(account, total)| (account.to_string(), total)).collect())
+        },
+        GroupBy::Week => {
+            let mut totals = week_totals(transactions);
+            if let Some(limit) = limit {
+                top_groups(&mut totals, limit);
+            }
+            ("Week", totals)
+        },
+        // Months are shown as 'Mar 2023', or '2023-03' if `iso_months` is true
+        GroupBy::Month => {
+            let mut totals = month_totals(transactions);
+            if let Some(limit) = limit {
+                top_groups(&mut totals, limit);
+            }
+            let format = if iso_months { "%Y-%m" } else { "%b %Y" };
+            ("Month", totals.into_iter().map(|(month, total)| (month.format(format).to_string(), total)).collect())
+        },
+    }
+}
+
+/// Print the result of a SELECT statement. If `is_auto_labelling` is true, transactions with labels are highlighted.
+pub(crate) fn render(result: &SelectResult, config: &Config, is_auto_labelling: bool) {
     let mut table = Table::new();
     table.remove_style(TableComponent::HorizontalLines);
     table.remove_style(TableComponent::MiddleIntersections);
     table.remove_style(TableComponent::LeftBorderIntersections);
     table.remove_style(TableComponent::RightBorderIntersections);
 
-    if group_by.is_some() {
-        group_by_label(transactions, &mut table);
-    } else {
-        handle_normal_select(transactions, &mut table, projection);
+    match result {
+        SelectResult::Transactions(transactions) => transactions_table(transactions, &mut table, is_auto_labelling, config),
+        SelectResult::Sum(total) => {
+            table.set_header(vec!["Subtotal"]);
+            table.add_row(vec![Cell::new(format_amount(*total)).set_alignment(CellAlignment::Right)]);
+        },
+        SelectResult::Count(count) => {
+            table.set_header(vec!["Count"]);
+            table.add_row(vec![Cell::new(count).set_alignment(CellAlignment::Right)]);
+        },
+        SelectResult::CountDistinct(field, count) => {
+            table.set_header(vec![match field {
+                Field::Description => "Count(distinct description)",
+                Field::Account => "Count(distinct account)",
+                Field::Label => "Count(distinct label)",
+            }]);
+            table.add_row(vec![Cell::new(count).set_alignment(CellAlignment::Right)]);
+        },
+        // Income and spending are broken out so it's clear where the net amount comes from
+        SelectResult::Net(income, spending) => {
+            table.set_header(vec!["Income", "Spending", "Net"]);
+            table.add_row(vec![
+                Cell::new(format_amount(*income)).set_alignment(CellAlignment::Right),
+                Cell::new(format_amount(*spending)).set_alignment(CellAlignment::Right),
+                Cell::new(format_amount(income + spending)).set_alignment(CellAlignment::Right),
+            ]);
+        },
+        SelectResult::Groups(header, totals) => {
+            table.set_header(vec![*header, "Amount"]);
+            for (group, amount) in totals {
+                table.add_row(vec![
+                    Cell::new(group),
+                    Cell::new(format_amount(*amount).as_str()).set_alignment(CellAlignment::Right)
+                ]);
+            }
+        },
     }
-}
 
-/// handles 'GROUP BY label'
-fn group_by_label(transactions: &[Transaction], table: &mut Table) {
-    table.set_header(vec!["Tag", "Amount"]);
+    print_paged(&table);
+}
 
+/// Total amount of each label, sorted by label. `label_amount` decides how the amount of a transaction with
+/// several labels is added to the totals.
+pub(crate) fn label_totals(transactions: &[Transaction], label_amount: LabelAmount) -> Vec<(&str, f32)> {
     let mut group_by_map: HashMap<&str, f32> = HashMap::new();
     for t in transactions {
-        for tag in &t.labels {
+        let (labels, amount) = match label_amount {
+            LabelAmount::Full => (&t.labels[..], t.amount),
+            LabelAmount::Split => (&t.labels[..], t.amount / t.labels.len() as f32),
+            LabelAmount::Primary => (&t.labels[..t.labels.len().min(1)], t.amount),
+        };
+        for tag in labels {
             let entry = group_by_map.entry(tag.as_str()).or_insert(0.0);
-            *entry += t.amount;
+            *entry += amount;
         }
     }
 
-    for (label, amount) in group_by_map {
-        table.add_row(vec![
-            Cell::new(label),
-            Cell::new(format_amount(amount).as_str()).set_alignment(CellAlignment::Right)
-        ]);
+    let mut totals: Vec<(&str, f32)> = group_by_map.into_iter().collect();
+    totals.sort_by(|a, b| a.0.cmp(b.0));
+    totals
+}
+
+/// Total amount of each account, sorted by account
+pub(crate) fn account_totals(transactions: &[Transaction]) -> Vec<(&str, f32)> {
+    let mut group_by_map: BTreeMap<&str, f32> = BTreeMap::new();
+    for t in transactions {
+        *group_by_map.entry(t.account.as_str()).or_insert(0.0) += t.amount;
     }
 
-    println!("{table}");
+    group_by_map.into_iter().collect()
 }
 
-fn handle_normal_select(transactions: &[Transaction], table: &mut Table, projection: &Projection) {
-    let mut is_normal_select = false;
-    let mut is_sum = false;
-    let mut is_count = false;
-    // Is auto labelling transactions
-    let mut is_auto_labelling = false;
+/// Sort groups in the given order. `totals` must be sorted by name, so groups with the same amount stay in
+/// alphabetical order. If `limit` is specified, only the groups with the largest amounts are kept.
+pub(crate) fn sort_groups(totals: &mut Vec<(&str, f32)>, limit: Option<usize>, order: GroupOrder) {
+    if let Some(limit) = limit {
+        top_groups(totals, limit);
+    }
+    match order {
+        GroupOrder::Amount { desc } => totals.sort_by(|a, b| {
+            let ordering = a.1.abs().total_cmp(&b.1.abs());
+            if desc { ordering.reverse() } else { ordering }
+        }),
+        GroupOrder::Name { desc } => totals.sort_by(|a, b| if desc { b.0.cmp(a.0) } else { a.0.cmp(b.0) }),
+    }
+}
 
-    match projection {
-        // SELECT * FROM ...
-        Projection::Star |
-        // SELECT 123 FROM ...
-        // Select by id has already been handled above
-        Projection::Id(_) => is_normal_select = true,
-
-        // SELECT SUM(*) FROM
-        // SELECT COUNT(*) FROM
-        Projection::Sum => is_sum = true,
-        Projection::Count => is_count = true,
-        Projection::Auto => {
-            is_normal_select = true;
-            is_auto_labelling = true;
+/// Sort groups by amount, largest spending or income first, and keep the first `limit` groups
+fn top_groups<T>(totals: &mut Vec<(T, f32)>, limit: usize) {
+    totals.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+    totals.truncate(limit);
+}
+
+/// Total amount of each ISO week, e.g. 2023-W05, sorted chronologically
+pub(crate) fn week_totals(transactions: &[Transaction]) -> Vec<(String, f32)> {
+    let mut group_by_map: BTreeMap<(i32, u32), f32> = BTreeMap::new();
+    for t in transactions {
+        let week = t.date.iso_week();
+        *group_by_map.entry((week.year(), week.week())).or_insert(0.0) += t.amount;
+    }
+
+    group_by_map.into_iter().map(|((year, week), total)| (format!("{year}-W{week:02}"), total)).collect()
+}
+
+/// Total amount of each month, keyed by the first day of month, sorted chronologically
+pub(crate) fn month_totals(transactions: &[Transaction]) -> Vec<(NaiveDate, f32)> {
+    let mut group_by_map: BTreeMap<NaiveDate, f32> = BTreeMap::new();
+    for t in transactions {
+        let first_day = t.date.date().with_day(1).unwrap();
+        *group_by_map.entry(first_day).or_insert(0.0) += t.amount;
+    }
+
+    group_by_map.into_iter().collect()
+}
+
+/// Max number of characters of descriptions in `SELECT *` results, from config and terminal width
+pub(crate) fn description_width(config: &Config) -> usize {
+    fit_terminal_width(config.description_width(), OTHER_COLUMNS_WIDTH)
+}
+
+/// Add selected transactions to table. Labels are coloured if printed to a terminal, except when auto labelling,
+/// which highlights whole rows.
+fn transactions_table(transactions: &[Transaction], table: &mut Table, is_auto_labelling: bool, config: &Config) {
+    let description_width = description_width(config);
+    let color_labels = !is_auto_labelling && stdout().is_terminal();
+    // Amounts as in the statement are only shown if any transaction had its amount inverted during import
+    let has_raw_amount = transactions.iter().any(|t| t.raw_amount.is_some());
+    let mut header = vec!["ID", "Account", "Date", "Description", "Amount", "Labels"];
+    if has_raw_amount {
+        header.insert(5, "Raw amount");
+    }
+    table.set_header(header);
+
+    for t in transactions {
+        let mut row = vec![
+            set_cell_style(t, Cell::new(t.id.to_string().as_str()), is_auto_labelling).set_alignment(CellAlignment::Right),
+            set_cell_style(t, Cell::new(t.account.as_str()), is_auto_labelling),
+            set_cell_style(t, Cell::new(format_date(t.date).as_str()), is_auto_labelling),
+            set_cell_style(t, Cell::new(truncate_with_ellipsis(&t.description, description_width)), is_auto_labelling),
+            set_cell_style(t, amount_cell(t), is_auto_labelling).set_alignment(CellAlignment::Right),
+            set_cell_style(t, Cell::new(if color_labels { colored_labels(&t.labels, config) } else { t.tags_display() }), is_auto_labelling)
+        ];
+        if has_raw_amount {
+            let raw_amount = t.raw_amount.map(format_amount).unwrap_or_default();
+            row.insert(5, set_cell_style(t, Cell::new(raw_amount), is_auto_labelling).set_alignment(CellAlignment::Right));
         }
+        table.add_row(row);
     }
+}
 
-    if is_normal_select {
-        table.set_header(vec!["ID", "Account", "Date", "Description", "Amount", "Labels"]);
+/// Print a table. If it doesn't fit in the terminal, print one page at a time and wait for a keypress
+/// before printing more, `space` for next page, `enter` for next line, `q` to stop.
+fn print_paged(table: &Table) {
+    let rendered = table.to_string();
+    let lines: Vec<&str> = rendered.lines().collect();
+    let rows = match terminal::size() {
+        Ok((_, rows)) if stdout().is_terminal() => rows as usize,
+        _ => 0
+    };
 
-        for t in transactions {
-            table.add_row(vec![
-                set_cell_style(t, Cell::new(t.id.to_string().as_str()), is_auto_labelling).set_alignment(CellAlignment::Right),
-                set_cell_style(t, Cell::new(t.account.as_str()), is_auto_labelling),
-                set_cell_style(t, Cell::new(format_date(t.date).as_str()), is_auto_labelling),
-                set_cell_style(t, Cell::new(t.description.as_str()), is_auto_labelling),
-                set_cell_style(t, Cell::new(format_amount(t.amount).as_str()), is_auto_labelling).set_alignment(CellAlignment::Right),
-                set_cell_style(t, Cell::new(t.tags_display().as_str()), is_auto_labelling)
-            ]);
+    // Last row of the terminal is used by the '--more--' prompt
+    if rows < 2 || lines.len() < rows {
+        println!("{rendered}");
+        return;
+    }
+
+    let mut printed = 0;
+    let mut step = rows - 1;
+    loop {
+        let end = (printed + step).min(lines.len());
+        for line in &lines[printed..end] {
+            println!("{line}");
+        }
+        printed = end;
+        if printed == lines.len() {
+            break;
         }
-    } else if is_sum {
-        table.set_header(vec!["Subtotal"]);
 
-        table.add_row(vec![Cell::new(format_amount(
-            transactions.iter().map(|t| t.amount).fold(0.0, |total, amount| total + amount))
-        ).set_alignment(CellAlignment::Right)]);
-    } else if is_count {
-        table.set_header(vec!["Count"]);
-        table.add_row(vec![Cell::new(transactions.len()).set_alignment(CellAlignment::Right)]);
+        match wait_for_more() {
+            Ok(Some(More::Page)) => step = rows - 1,
+            Ok(Some(More::Line)) => step = 1,
+            _ => break
+        }
     }
+}
 
-    println!("{table}");
+enum More {
+    Page,
+    Line,
 }
 
+/// Show '--more--' prompt and wait for a keypress. Returns `None` if user wants to stop.
+fn wait_for_more() -> Result<Option<More>, std::io::Error> {
+    print!("--more--");
+    stdout().flush()?;
+    terminal::enable_raw_mode()?;
+    let more = loop {
+        if let Event::Key(event) = read()? {
+            match event.code {
+                KeyCode::Char(' ') | KeyCode::PageDown => break Some(More::Page),
+                KeyCode::Enter | KeyCode::Down | KeyCode::Char('j') => break Some(More::Line),
+                KeyCode::Char('q') | KeyCode::Esc => break None,
+                _ => {}
+            }
+        }
+    };
+    terminal::disable_raw_mode()?;
+    // Remove the prompt so the table continues on the same line
+    execute!(stdout(), MoveToColumn(0), terminal::Clear(ClearType::CurrentLine))?;
+    Ok(more)
+}
 
 fn set_cell_style(t: &Transaction, cell: Cell, is_tagging: bool) -> Cell {
     if is_tagging && !t.labels.is_empty() {
@@ -136,11 +398,200 @@ fn set_cell_style(t: &Transaction, cell: Cell, is_tagging: bool) -> Cell {
     }
 }
 
+/// Amount cell coloured green for income and red for spending.
+/// Rows highlighted by auto labelling override the colour, see `set_cell_style`.
+fn amount_cell(t: &Transaction) -> Cell {
+    let cell = Cell::new(format_transaction_amount(t).as_str());
+    if t.amount > 0.0 {
+        cell.fg(Color::Green)
+    } else if t.amount < 0.0 {
+        cell.fg(Color::Red)
+    } else {
+        cell
+    }
+}
+
 /// Format $ amount
-fn format_amount(amount: f32) -> String {
+pub(crate) fn format_amount(amount: f32) -> String {
     format!("{amount:.2}")
 }
 
-fn format_date(date: NaiveDateTime) -> String {
+/// Format amount of a transaction, followed by its currency if there is one
+fn format_transaction_amount(t: &Transaction) -> String {
+    match &t.currency {
+        Some(currency) => format!("{} {currency}", format_amount(t.amount)),
+        None => format_amount(t.amount)
+    }
+}
+
+pub(crate) fn format_date(date: NaiveDateTime) -> String {
     date.format("%Y-%m-%d").to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+    use crate::config::Config;
+    use crate::controller::select::{account_totals, execute_select, footer, format_amount, label_totals, month_totals, sort_groups, top_groups, week_totals, SelectResult};
+    use crate::csv_reader::Record;
+    use crate::db::{test_db, Database};
+    use crate::parser::{parse, Field, GroupOrder, LabelAmount, Statement};
+    use crate::transaction::Transaction;
+    use crate::util::month_of;
+
+    /// Parse and run a SELECT statement
+    fn select(db: &mut Database, sql: &str) -> SelectResult {
+        match parse(sql).unwrap().1 {
+            Statement::Select(projection, from, condition, order_by, limit, group_by) =>
+                execute_select(db, projection, from, condition, order_by, limit, group_by, &Config::empty(), ""),
+            statement => panic!("Not a SELECT statement: {statement:?}")
+        }
+    }
+
+    fn ids(result: SelectResult) -> Vec<u32> {
+        match result {
+            SelectResult::Transactions(transactions) => transactions.iter().map(|t| t.id).collect(),
+            result => panic!("Not a list of transactions: {result:?}")
+        }
+    }
+
+    fn groups(names_and_totals: &[(&str, f32)]) -> Vec<(String, f32)> {
+        names_and_totals.iter().map(|(name, total)| (name.to_string(), *total)).collect()
+    }
+
+    #[test]
+    fn test_select() {
+        let mut db = test_db();
+        assert_eq!(ids(select(&mut db, "SELECT * WHERE label = 'grocery'")), vec![1, 3]);
+        assert_eq!(ids(select(&mut db, "SELECT * FROM amex WHERE desc like 'sydney' OR amount < -100")), vec![3, 4]);
+        assert_eq!(ids(select(&mut db, "SELECT spending ORDER BY amount LIMIT 2")), vec![4, 3]);
+        assert_eq!(ids(select(&mut db, "SELECT TOP 1 spending")), vec![4]);
+        assert_eq!(ids(select(&mut db, "SELECT 2")), vec![2]);
+        assert_eq!(ids(select(&mut db, "SELECT * WHERE month = 2024-01")), Vec::<u32>::new());
+        assert_eq!(ids(select(&mut db, "SELECT * WHERE NOT month = 2023-02")), vec![1, 2]);
+        assert_eq!(ids(select(&mut db, "SELECT * WHERE NOT label = 'grocery' AND NOT year = 2024")), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_select_not_month() {
+        let mut db = test_db();
+        // 'month = 12' is the last December, before and after it are not excluded
+        let december = month_of(12).start;
+        for (id, date) in [(5, december), (6, december.with_day(31).unwrap()), (7, december - Duration::days(1)), (8, december + Duration::days(31))] {
+            db.upsert(&Record { id: Some(id), date: date.and_hms_opt(0, 0, 0).unwrap(), ..Record::new("cba", "2023-01-01", "test", -1.0) });
+        }
+        assert_eq!(ids(select(&mut db, "SELECT * WHERE month = 12")), vec![5, 6]);
+        assert_eq!(ids(select(&mut db, "SELECT * WHERE NOT month = 12")), vec![1, 2, 3, 4, 7, 8]);
+    }
+
+    #[test]
+    fn test_select_aggregates() {
+        let mut db = test_db();
+        assert_eq!(select(&mut db, "SELECT SUM(*)"), SelectResult::Sum(2749.5));
+        assert_eq!(select(&mut db, "SELECT SUM(spending) FROM amex"), SelectResult::Sum(-200.5));
+        assert_eq!(select(&mut db, "SELECT SUM(turnover)"), SelectResult::Sum(3250.5));
+        assert_eq!(select(&mut db, "SELECT SUM(turnover) WHERE turnover > 100"), SelectResult::Sum(3120.0));
+        assert_eq!(select(&mut db, "SELECT COUNT(*) WHERE desc like 'sydney'"), SelectResult::Count(2));
+        assert_eq!(select(&mut db, "SELECT COUNT(DISTINCT label)"), SelectResult::CountDistinct(Field::Label, 2));
+        assert_eq!(select(&mut db, "SELECT NET WHERE month = 2023-01"), SelectResult::Net(3000.0, -50.0));
+
+        // No transactions, both are 0.00 rather than -0.00
+        match select(&mut db, "SELECT NET WHERE year = 2000") {
+            SelectResult::Net(income, spending) => assert_eq!((format_amount(income), format_amount(spending)), ("0.00".to_string(), "0.00".to_string())),
+            result => panic!("Unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_select_group_by() {
+        let mut db = test_db();
+        assert_eq!(select(&mut db, "SELECT spending GROUP BY label"), SelectResult::Groups("Tag", groups(&[("food", -200.5), ("grocery", -130.5)])));
+        assert_eq!(select(&mut db, "SELECT spending GROUP BY primary_label"), SelectResult::Groups("Primary tag", groups(&[("grocery", -130.5), ("food", -120.0)])));
+        assert_eq!(select(&mut db, "SELECT spending LIMIT 1 GROUP BY label SPLIT"), SelectResult::Groups("Tag", groups(&[("food", -160.25)])));
+        assert_eq!(select(&mut db, "SELECT * GROUP BY account ORDER BY account"), SelectResult::Groups("Account", groups(&[("amex", -200.5), ("cba", 2950.0)])));
+        assert_eq!(select(&mut db, "SELECT * WHERE year = 2023 GROUP BY month"), SelectResult::Groups("Month", groups(&[("Jan 2023", 2950.0), ("Feb 2023", -200.5)])));
+        assert_eq!(select(&mut db, "SELECT COUNT(*) GROUP BY week"), SelectResult::Groups("Week", groups(&[("2023-W01", -50.0), ("2023-W03", 3000.0), ("2023-W05", -80.5), ("2023-W07", -120.0)])));
+    }
+
+    #[test]
+    fn test_week_totals() {
+        let transactions: Vec<Transaction> = [("2023-01-02T00:00:00", -10.0), ("2023-01-01T00:00:00", -5.0), ("2023-01-08T00:00:00", 20.0), ("2023-01-03T00:00:00", -2.5)]
+            .iter().enumerate()
+            .map(|(i, (date, amount))| Transaction::new(i as u32, "cba".into(), NaiveDateTime::from_str(date).unwrap(), "test", *amount, vec![]))
+            .collect();
+
+        // 2023-01-01 is a Sunday, which belongs to the last ISO week of 2022
+        assert_eq!(week_totals(&transactions), vec![("2022-W52".to_string(), -5.0), ("2023-W01".to_string(), 7.5)]);
+        assert_eq!(footer(&transactions), "4 transactions, total 2.50");
+        assert_eq!(footer(&transactions[..1]), "1 transaction, total -10.00");
+        assert_eq!(footer(&[]), "0 transactions, total 0.00");
+    }
+
+    #[test]
+    fn test_month_totals() {
+        let transactions: Vec<Transaction> = [("2023-12-31T23:00:00", -10.0), ("2023-02-01T00:00:00", -5.0), ("2023-12-01T00:00:00", 20.0), ("2024-01-01T00:00:00", -2.5)]
+            .iter().enumerate()
+            .map(|(i, (date, amount))| Transaction::new(i as u32, "cba".into(), NaiveDateTime::from_str(date).unwrap(), "test", *amount, vec![]))
+            .collect();
+
+        // Sorted chronologically rather than by the displayed month name
+        let totals = month_totals(&transactions);
+        assert_eq!(totals, vec![
+            (NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(), -5.0),
+            (NaiveDate::from_ymd_opt(2023, 12, 1).unwrap(), 10.0),
+            (NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), -2.5),
+        ]);
+        assert_eq!(totals[1].0.format("%b %Y").to_string(), "Dec 2023");
+    }
+
+    #[test]
+    fn test_label_totals() {
+        let date = NaiveDateTime::from_str("2023-01-01T00:00:00").unwrap();
+        let transactions = vec![
+            Transaction::new(1, "cba".into(), date, "Coles", -80.0, vec!["grocery".into(), "food".into()]),
+            Transaction::new(2, "cba".into(), date, "Restaurant", -20.0, vec!["food".into()]),
+            Transaction::new(3, "cba".into(), date, "Salary", 3000.0, vec![]),
+        ];
+
+        // A transaction with two labels is counted in both labels, unless only primary labels are used
+        assert_eq!(label_totals(&transactions, LabelAmount::Full), vec![("food", -100.0), ("grocery", -80.0)]);
+        assert_eq!(label_totals(&transactions, LabelAmount::Primary), vec![("food", -20.0), ("grocery", -80.0)]);
+
+        // Split totals add up to the total amount of labelled transactions
+        assert_eq!(label_totals(&transactions, LabelAmount::Split), vec![("food", -60.0), ("grocery", -40.0)]);
+    }
+
+    #[test]
+    fn test_top_groups() {
+        let mut totals = vec![("coffee", -20.0), ("grocery", -600.0), ("salary", 3000.0), ("transport", -80.0)];
+        top_groups(&mut totals, 3);
+        assert_eq!(totals, vec![("salary", 3000.0), ("grocery", -600.0), ("transport", -80.0)]);
+    }
+
+    #[test]
+    fn test_sort_groups() {
+        let date = NaiveDateTime::from_str("2023-01-01T00:00:00").unwrap();
+        let transactions: Vec<Transaction> = [("cba", -50.0), ("amex", -80.0), ("westpac", 80.0), ("cba", 3000.0), ("bankwest", -5.0)]
+            .iter().enumerate()
+            .map(|(i, (account, amount))| Transaction::new(i as u32, account.to_string(), date, "test", *amount, vec![]))
+            .collect();
+
+        // Largest spending or income first, accounts with the same amount are in alphabetical order
+        let mut totals = account_totals(&transactions);
+        sort_groups(&mut totals, None, GroupOrder::Amount { desc: true });
+        assert_eq!(totals, vec![("cba", 2950.0), ("amex", -80.0), ("westpac", 80.0), ("bankwest", -5.0)]);
+
+        let mut totals = account_totals(&transactions);
+        sort_groups(&mut totals, Some(3), GroupOrder::Name { desc: false });
+        assert_eq!(totals, vec![("amex", -80.0), ("cba", 2950.0), ("westpac", 80.0)]);
+
+        let mut totals = account_totals(&transactions);
+        sort_groups(&mut totals, None, GroupOrder::Amount { desc: false });
+        assert_eq!(totals, vec![("bankwest", -5.0), ("amex", -80.0), ("westpac", 80.0), ("cba", 2950.0)]);
+
+        let mut totals = account_totals(&transactions);
+        sort_groups(&mut totals, None, GroupOrder::Name { desc: true });
+        assert_eq!(totals, vec![("westpac", 80.0), ("cba", 2950.0), ("bankwest", -5.0), ("amex", -80.0)]);
+    }
+}
@@ -0,0 +1,56 @@
+use comfy_table::{Cell, CellAlignment, Table, TableComponent};
+use log::info;
+use crate::controller::select::{format_amount, format_date};
+use crate::db::Database;
+
+/// Print labels suggested for a transaction, by majority vote of similar labelled transactions
+pub(crate) fn execute_suggest(db: &Database, trans_id: u32) {
+    let (suggested, neighbours) = db.suggest_labels(trans_id);
+    if suggested.is_empty() {
+        info!("No labels to suggest, not enough similar labelled transactions.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table.remove_style(TableComponent::HorizontalLines);
+    table.remove_style(TableComponent::MiddleIntersections);
+    table.remove_style(TableComponent::LeftBorderIntersections);
+    table.remove_style(TableComponent::RightBorderIntersections);
+    table.set_header(vec!["Label", "Votes"]);
+    for (label, votes) in &suggested {
+        table.add_row(vec![Cell::new(label), Cell::new(format!("{votes}/{neighbours}")).set_alignment(CellAlignment::Right)]);
+    }
+    println!("{table}");
+
+    let labels: Vec<&str> = suggested.iter().map(|(label, _)| label.as_str()).collect();
+    println!("To apply: LABEL {trans_id} {};", labels.join(" "));
+}
+
+/// Print transactions similar to the given transaction, most similar first
+pub(crate) fn execute_similar(db: &mut Database, trans_id: u32, limit: Option<usize>) {
+    let similar = db.similar(trans_id, limit.unwrap_or(10));
+    if similar.is_empty() {
+        info!("No similar transactions found.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table.remove_style(TableComponent::HorizontalLines);
+    table.remove_style(TableComponent::MiddleIntersections);
+    table.remove_style(TableComponent::LeftBorderIntersections);
+    table.remove_style(TableComponent::RightBorderIntersections);
+    table.set_header(vec!["ID", "Account", "Date", "Description", "Amount", "Labels", "Similarity"]);
+    for (t, score) in similar {
+        table.add_row(vec![
+            Cell::new(t.id).set_alignment(CellAlignment::Right),
+            Cell::new(t.account.as_str()),
+            Cell::new(format_date(t.date)),
+            Cell::new(t.description.as_str()),
+            Cell::new(format_amount(t.amount)).set_alignment(CellAlignment::Right),
+            Cell::new(t.tags_display()),
+            Cell::new(format!("{score:.2}")).set_alignment(CellAlignment::Right),
+        ]);
+    }
+
+    println!("{table}");
+}
@@ -17,6 +17,8 @@ pub(crate) struct ColumnInfo {
     pub(crate) description_column: usize,
     pub(crate) amount_column: usize,
     pub(crate) credit_amount_column: Option<usize>,
+    /// Running balance of the account, used to reconcile imported transactions
+    pub(crate) balance_column: Option<usize>,
 }
 
 pub(crate) fn parse_csv_column_with_header(headers: &StringRecord) -> Result<ColumnInfo, CsvError> {
@@ -90,6 +92,9 @@ pub(crate) fn parse_csv_column_with_header(headers: &StringRecord) -> Result<Col
         }
     }
 
+    let balance_regex = Regex::new(r"(?i)balance").unwrap();
+    let balance_index = headers.iter().position(|s| balance_regex.is_match(s));
+
     Ok(ColumnInfo {
         has_header: true,
         perfidb_transaction_id_column,
@@ -99,13 +104,14 @@ pub(crate) fn parse_csv_column_with_header(headers: &StringRecord) -> Result<Col
         description_column: description_index.unwrap(),
         amount_column: debit_amount_index.unwrap(),
         credit_amount_column: credit_amount_index,
+        balance_column: balance_index,
     })
 }
 
 
 
-pub(crate) fn parse_csv_column_no_header(csv_path: &Path) -> ColumnInfo {
-    let mut reader = csv::ReaderBuilder::new().has_headers(false).from_path(csv_path).unwrap();
+pub(crate) fn parse_csv_column_no_header(csv_path: &Path, delimiter: u8) -> ColumnInfo {
+    let mut reader = csv::ReaderBuilder::new().has_headers(false).delimiter(delimiter).from_path(csv_path).unwrap();
     let mut rows :Vec<StringRecord> = vec![];
 
     // Read up to first 5 rows
@@ -142,6 +148,7 @@ pub(crate) fn parse_csv_column_no_header(csv_path: &Path) -> ColumnInfo {
         amount_column: amount_column_index.unwrap(),
         description_column: description_column_index.unwrap(),
         credit_amount_column: None,
+        balance_column: None,
     }
 }
 
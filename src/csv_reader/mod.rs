@@ -1,4 +1,6 @@
 use std::{fmt};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::ops::Index;
 use std::path::Path;
 use chrono::{NaiveDate, NaiveDateTime};
@@ -18,6 +20,35 @@ pub(crate) struct Record {
     pub(crate) description: String,
     pub(crate) amount: f32,
     pub(crate) labels: Option<Vec<String>>,
+    pub(crate) currency: Option<String>,
+    /// Account balance after this transaction, if the statement has a balance column
+    pub(crate) balance: Option<f32>,
+    /// Amount as in the statement before it was inverted during import, `None` if not inverted
+    pub(crate) raw_amount: Option<f32>,
+}
+
+#[cfg(test)]
+impl Record {
+    /// Record of a transaction at midnight of `date`, e.g. '2023-01-05', without labels
+    pub(crate) fn new(account: &str, date: &str, description: &str, amount: f32) -> Record {
+        Record {
+            id: None,
+            account: account.to_string(),
+            date: date.parse::<NaiveDate>().unwrap().and_hms_opt(0, 0, 0).unwrap(),
+            description: description.to_string(),
+            amount,
+            labels: None,
+            currency: None,
+            balance: None,
+            raw_amount: None,
+        }
+    }
+
+    /// Same record with the labels, or without labels if empty
+    pub(crate) fn with_labels(self, labels: &[&str]) -> Record {
+        let labels = if labels.is_empty() { None } else { Some(labels.iter().map(|label| label.to_string()).collect()) };
+        Record { labels, ..self }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -41,12 +72,21 @@ impl fmt::Display for CsvError {
 
 impl std::error::Error for CsvError {}
 
+#[cfg(test)]
 pub(crate) fn read_transactions(table_name :&str, file_path: &Path) -> Result<Vec<Record>, CsvError> {
+    stream_transactions(table_name, file_path)?.collect()
+}
+
+/// Read transactions from a csv file lazily. Records are parsed one row at a time as the iterator advances,
+/// so a large statement file does not need to be loaded into memory.
+/// Rows with an invalid amount are returned as errors.
+pub(crate) fn stream_transactions(table_name :&str, file_path: &Path) -> Result<impl Iterator<Item = Result<Record, CsvError>>, CsvError> {
     if !file_path.exists() {
         return Err(CsvError::FileNotFoundError("File not found".into()));
     }
 
-    let header_row = detect_header_row(file_path);
+    let delimiter = detect_delimiter(file_path);
+    let header_row = detect_header_row(file_path, delimiter);
 
     let column_info = match &header_row {
         Some(header_row) => {
@@ -54,53 +94,86 @@ pub(crate) fn read_transactions(table_name :&str, file_path: &Path) -> Result<Ve
             column::parse_csv_column_with_header(header_row)?
         },
         None => {
-            column::parse_csv_column_no_header(file_path)
+            column::parse_csv_column_no_header(file_path, delimiter)
         }
     };
 
-    let mut rdr = csv::ReaderBuilder::new().has_headers(column_info.has_header).from_path(file_path).unwrap();
-    let mut records :Vec<Record> = vec![];
-    for record in rdr.records() {
-        let row = record.unwrap();
-        let date = parse_date(row.get(column_info.date_column).unwrap());
-        let description = row.get(column_info.description_column).unwrap().to_string();
-        let amount = parse_amount(&row, &column_info);
-
-        let id = column_info.perfidb_transaction_id_column.map(|i| row.index(i).parse::<u32>().unwrap());
-
-        let account = match column_info.perfidb_account_column {
-            Some(i) => row.index(i).to_string(),
-            None => table_name.to_string()
-        };
-
-        let labels: Option<Vec<String>> = match column_info.perfidb_label_column {
-            Some(i) => {
-                match row.index(i) {
-                    "" => None,
-                    _ => Some(row.index(i).split('|').map(str::to_string).collect())
-                }
-            },
-            None => None
-        };
-
-        records.push(Record {
-            id,
-            account,
-            date,
-            description,
-            amount,
-            labels
-        });
+    let rdr = csv::ReaderBuilder::new().has_headers(column_info.has_header).delimiter(delimiter).from_path(file_path).unwrap();
+    let table_name = table_name.to_string();
+    Ok(rdr.into_records().map(move |record| parse_record(&record.unwrap(), &column_info, &table_name)))
+}
+
+/// Parse a single csv row into a transaction record
+fn parse_record(row: &StringRecord, column_info: &ColumnInfo, table_name: &str) -> Result<Record, CsvError> {
+    let date = parse_date(row.get(column_info.date_column).unwrap());
+    let description = row.get(column_info.description_column).unwrap().to_string();
+    let amount = parse_amount(row, column_info)?;
+
+    let id = column_info.perfidb_transaction_id_column.map(|i| row.index(i).parse::<u32>().unwrap());
+
+    let account = match column_info.perfidb_account_column {
+        Some(i) => row.index(i).to_string(),
+        None => table_name.to_string()
+    };
+
+    let balance = column_info.balance_column
+        .and_then(|i| row.get(i))
+        .and_then(|balance| balance.replace(['$', ','], "").trim().parse::<f32>().ok())
+        .filter(|balance| balance.is_finite());
+
+    let labels: Option<Vec<String>> = match column_info.perfidb_label_column {
+        Some(i) => {
+            match row.index(i) {
+                "" => None,
+                _ => Some(row.index(i).split('|').map(str::to_string).collect())
+            }
+        },
+        None => None
+    };
+
+    Ok(Record {
+        id,
+        account,
+        date,
+        description,
+        amount,
+        labels,
+        currency: None,
+        balance,
+        raw_amount: None,
+    })
+}
+
+/// Detect the delimiter of a statement file, which is comma, tab or semicolon, whichever appears most often outside
+/// quotes in the first line. Comma is used if none of them appears.
+fn detect_delimiter(csv_path: &Path) -> u8 {
+    let mut first_line = String::new();
+    if let Ok(file) = File::open(csv_path) {
+        // Not valid UTF-8 is handled by the csv reader later, the first line is left empty here
+        let _ = BufReader::new(file).read_line(&mut first_line);
     }
 
-    Ok(records)
+    let mut counts = [(b',', 0), (b'\t', 0), (b';', 0)];
+    let mut in_quotes = false;
+    for c in first_line.bytes() {
+        if c == b'"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes {
+            if let Some(count) = counts.iter_mut().find(|(delimiter, _)| *delimiter == c) {
+                count.1 += 1;
+            }
+        }
+    }
+
+    // On a tie the earlier delimiter wins, so comma is preferred
+    counts.iter().rev().max_by_key(|(_, count)| *count).filter(|(_, count)| *count > 0).map(|(delimiter, _)| *delimiter).unwrap_or(b',')
 }
 
 /// Try detecting if the first row of csv file is a 'header' row.
 /// Most bank statements should include a header row, e.g. "date | amount | description". Some banks' statement does not
 /// include a header row, the first row is the first transaction data.
-fn detect_header_row(csv_path: &Path) -> Option<StringRecord> {
-    let mut csv_reader = csv::ReaderBuilder::new().has_headers(false).from_path(csv_path).unwrap();
+fn detect_header_row(csv_path: &Path, delimiter: u8) -> Option<StringRecord> {
+    let mut csv_reader = csv::ReaderBuilder::new().has_headers(false).delimiter(delimiter).from_path(csv_path).unwrap();
     let mut first_row = StringRecord::new();
     csv_reader.read_record(&mut first_row).unwrap();
 
@@ -154,6 +227,9 @@ fn count_digits(s: &str) -> u32 {
 }
 
 
+/// Format of date with time, e.g. 2022-07-31T17:30:45
+pub(crate) const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
 fn parse_date(s :&str) -> NaiveDateTime {
     let yyyymmdd_t_hhmmss = Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}$").unwrap();
     let yyyymmdd_t_hhmmss_zone = Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\+.+$").unwrap();
@@ -161,9 +237,9 @@ fn parse_date(s :&str) -> NaiveDateTime {
     let ddmmmyyyy = Regex::new(r"^\d{1,2} [a-zA-Z]{3} \d{4}$").unwrap();
 
     if yyyymmdd_t_hhmmss.is_match(s) {
-        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()
+        NaiveDateTime::parse_from_str(s, DATETIME_FORMAT).unwrap()
     } else if yyyymmdd_t_hhmmss_zone.is_match(s) {
-        NaiveDateTime::parse_from_str(&s[0..19], "%Y-%m-%dT%H:%M:%S").unwrap()
+        NaiveDateTime::parse_from_str(&s[0..19], DATETIME_FORMAT).unwrap()
     } else if ddmmyyyy.is_match(s) {
         NaiveDate::parse_from_str(s, "%d/%m/%Y").unwrap().and_hms_opt(0, 0, 0).unwrap()
     } else if ddmmmyyyy.is_match(s) {
@@ -173,10 +249,10 @@ fn parse_date(s :&str) -> NaiveDateTime {
     }
 }
 
-fn parse_amount(row: &StringRecord, header_index: &ColumnInfo) -> f32 {
+fn parse_amount(row: &StringRecord, header_index: &ColumnInfo) -> Result<f32, CsvError> {
     if header_index.credit_amount_column.is_none() {
         let amount_str = row.get(header_index.amount_column).unwrap().replace(['$', ','], "");
-        return amount_str.trim().parse::<f32>().unwrap();
+        return parse_finite(amount_str.trim());
     }
 
     // if we get here it means there is a 'credit amount' column.
@@ -184,11 +260,19 @@ fn parse_amount(row: &StringRecord, header_index: &ColumnInfo) -> f32 {
     // first check if debit amount is empty
     let amount_str = row.get(header_index.amount_column).unwrap().replace(['$', ','], "");
     if !amount_str.is_empty() {
-        -amount_str.parse::<f32>().unwrap()
+        parse_finite(&amount_str).map(|amount| -amount)
     } else {
-        row.get(header_index.credit_amount_column.unwrap()).unwrap().replace(['$', ','], "").parse::<f32>().unwrap()
+        parse_finite(&row.get(header_index.credit_amount_column.unwrap()).unwrap().replace(['$', ','], ""))
+    }
+}
+
+/// Parse an amount, rejecting 'nan', 'inf' and values too large for f32
+fn parse_finite(amount_str: &str) -> Result<f32, CsvError> {
+    match amount_str.parse::<f32>() {
+        Ok(amount) if amount.is_finite() => Ok(amount),
+        _ => Err(CsvError::InvalidFileError(format!("Invalid amount '{amount_str}'")))
     }
 }
 
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
@@ -1,9 +1,9 @@
 use std::path::PathBuf;
-use crate::csv_reader::{detect_header_row, read_transactions};
+use crate::csv_reader::{detect_delimiter, detect_header_row, read_transactions, stream_transactions};
 
 #[test]
 fn test_detect_header_row() {
-    let result = detect_header_row(&fixture_filename("header.csv"));
+    let result = detect_header_row(&fixture_filename("header.csv"), b',');
     match result {
         Some(header_row) => {
             assert_eq!(header_row.get(0), Some("Time"));
@@ -36,6 +36,55 @@ fn test_read_no_header() {
     }
 }
 
+#[test]
+fn test_stream_transactions() {
+    let mut records = stream_transactions("amex", &fixture_filename("header.csv")).unwrap();
+    let first = records.next().unwrap().unwrap();
+    assert_eq!(first.account, "amex");
+    assert_eq!(first.amount, -12.40);
+    assert_eq!(records.count(), 3);
+}
+
+#[test]
+fn test_read_balance() {
+    let rows = read_transactions("cba", &fixture_filename("balance.csv")).unwrap();
+    assert_eq!(rows[0].amount, -50.0);
+    assert_eq!(rows[0].balance, Some(1950.0));
+    assert_eq!(rows[1].balance, Some(4950.0));
+
+    // No balance column
+    let rows = read_transactions("amex", &fixture_filename("header.csv")).unwrap();
+    assert_eq!(rows[0].balance, None);
+}
+
+#[test]
+fn test_read_other_delimiters() {
+    // Commas within values don't count, they are quoted or not in the first line
+    assert_eq!(detect_delimiter(&fixture_filename("header.csv")), b',');
+    assert_eq!(detect_delimiter(&fixture_filename("tab.tsv")), b'\t');
+    assert_eq!(detect_delimiter(&fixture_filename("semicolon.csv")), b';');
+
+    let rows = read_transactions("cba", &fixture_filename("tab.tsv")).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].description, "WOOLWORTHS, SYDNEY");
+    assert_eq!(rows[0].amount, -50.0);
+    assert_eq!(rows[1].balance, Some(4950.0));
+
+    let rows = read_transactions("cba", &fixture_filename("semicolon.csv")).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].description, "WOOLWORTHS; SYDNEY");
+    assert_eq!(rows[1].amount, 3000.0);
+}
+
+#[test]
+fn test_read_invalid_amount() {
+    let result = read_transactions("amex", &fixture_filename("nan_amount.csv"));
+    assert_eq!(result.unwrap_err().to_string(), "csv reading error: Invalid amount 'nan'");
+
+    let result = read_transactions("amex", &fixture_filename("inf_amount.csv"));
+    assert_eq!(result.unwrap_err().to_string(), "csv reading error: Invalid amount 'inf'");
+}
+
 /// Return the path to a file within the test data directory
 pub(crate) fn fixture_filename(filename: &str) -> PathBuf {
     let mut dir = fixture_dir();
@@ -0,0 +1,64 @@
+use std::collections::{BTreeMap, HashMap};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Database content added after the main database file format was fixed.
+///
+/// The main database content is serialised with bincode, which does not allow adding new fields without breaking
+/// existing database files. The extension is written as JSON after the main content, every field falls back to its
+/// default value when missing, so new fields can be added here without breaking database files written by older versions.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub(crate) struct Extension {
+    /// Currency of transactions, keyed by transaction id. Transactions without currency are not stored.
+    pub(crate) currencies: HashMap<u32, String>,
+
+    /// Account balance after each transaction, keyed by transaction id. Only available for transactions
+    /// imported from statements with a balance column.
+    pub(crate) balances: HashMap<u32, f32>,
+
+    /// Amount as in the statement, keyed by transaction id. Only available for transactions whose amount was
+    /// inverted during import, so the sign can be checked against the statement.
+    pub(crate) raw_amounts: HashMap<u32, f32>,
+
+    /// Local time when the transaction was added to database, keyed by transaction id. Transactions added by
+    /// older versions don't have an import time.
+    pub(crate) imported_at: HashMap<u32, NaiveDateTime>,
+
+    /// Statement file a transaction was imported from, keyed by transaction id. Transactions inserted manually or
+    /// imported by older versions don't have a source file.
+    pub(crate) source_files: HashMap<u32, String>,
+
+    /// Files imported by the last IMPORT statement, which can be undone with ROLLBACK LAST IMPORT
+    pub(crate) last_import: Vec<String>,
+
+    /// Saved SELECT statements, keyed by lowercase view name
+    pub(crate) views: BTreeMap<String, String>,
+}
+
+impl Extension {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> serde_json::Result<Extension> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::extension::Extension;
+
+    #[test]
+    fn test() {
+        // Fields missing in saved extension fall back to default
+        let extension = Extension::from_bytes(b"{}").unwrap();
+        assert!(extension.currencies.is_empty());
+
+        let mut extension = Extension::default();
+        extension.currencies.insert(3, "USD".to_string());
+        let extension = Extension::from_bytes(&extension.to_bytes()).unwrap();
+        assert_eq!(extension.currencies.get(&3), Some(&"USD".to_string()));
+    }
+}
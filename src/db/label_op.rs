@@ -16,6 +16,12 @@ pub(crate) enum LabelCommand {
 
     /// When auto labelling is specified, all existing labels are wiped out
     Auto,
+
+    /// Auto labelling transactions without labels, labelled transactions are not changed
+    AutoUnlabelled,
+
+    /// Add labels from auto labelling rules, existing labels are kept
+    AutoAdd,
 }
 
 /// Represent a labelling operation, i.e. add a label, remove a label
@@ -88,5 +94,6 @@ mod tests {
         let (input, label_ops) = parse_label_ops(" abc -def - ' xyz mmm'  ").unwrap();
         assert_eq!(input, "  ");
         assert_eq!(label_ops[0], LabelOp { label: String::from("abc"), op: Operation::Add });
+        assert_eq!(label_ops[2], LabelOp { label: String::from("xyz mmm"), op: Operation::Remove });
     }
 }
@@ -37,7 +37,22 @@ impl StringMinHash {
         self.id_to_string.get(hash)
     }
 
+    /// Remove all strings whose id doesn't satisfy the predicate. Return number of strings removed.
+    /// Removed ids are never reused.
+    pub(crate) fn retain<F>(&mut self, mut keep: F) -> usize where F: FnMut(&u32) -> bool {
+        let before = self.id_to_string.len();
+        self.id_to_string.retain(|id, _| keep(id));
+        self.string_to_id.retain(|_, id| self.id_to_string.contains_key(id));
+        before - self.id_to_string.len()
+    }
+
     pub(crate) fn lookup_by_string<S>(&self, str: S) -> Option<u32> where S: Into<String> {
         self.string_to_id.get(&str.into().to_lowercase()).copied()
     }
+
+    /// Return ids of all strings containing the keyword, case-insensitive.
+    pub(crate) fn lookup_containing(&self, keyword: &str) -> Vec<u32> {
+        let keyword = keyword.to_lowercase();
+        self.string_to_id.iter().filter(|(s, _)| s.contains(&keyword)).map(|(_, id)| *id).collect()
+    }
 }
@@ -2,32 +2,42 @@ mod search;
 mod minhash;
 mod roaring_bitmap;
 mod label_id_vec;
+mod extension;
+mod query_cache;
 pub(crate) mod label_op;
-pub(crate) mod shadow;
 
 use std::fs;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::collections::hash_map::Entry;
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::path::{Path};
+use std::rc::Rc;
 use anyhow::Context;
 
-use chrono::{NaiveDate, NaiveDateTime};
-use log::{debug};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use log::{debug, warn};
 use md5::Digest;
+use regex::Regex;
+use roaring::RoaringBitmap;
 use serde::{Deserialize, Serialize};
 
 use crate::csv_reader::Record;
 use minhash::StringMinHash;
-use crate::config::Config;
+use crate::db::extension::Extension;
 use crate::db::label_id_vec::LabelIdVec;
 use crate::db::label_op::{LabelCommand, LabelOp};
+use crate::db::query_cache::QueryCache;
 use crate::db::roaring_bitmap::PerfidbRoaringBitmap;
 use crate::db::search::SearchIndex;
-use crate::parser::{Condition, Operator, OrderBy, OrderByField};
+use crate::parser::{AmountKind, Condition, Operator, OrderBy, OrderByField};
+use crate::config::Config;
 use crate::labeller::Labeller;
-use crate::transaction::Transaction;
+use crate::transaction::{transaction_hash, Transaction};
+
+/// Number of most similar labelled transactions voting for suggested labels
+const SUGGEST_LABELS_NEIGHBOURS: usize = 5;
 
 /// perfidb binary version
 const PERFIDB_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -66,6 +76,16 @@ pub(crate) struct Database {
     /// Key is transaction date, value is a list of transaction ids.
     date_index: BTreeMap<NaiveDate, PerfidbRoaringBitmap>,
 
+    /// Key is transaction amount in cents, value is a list of transaction ids.
+    /// Not persisted, it is rebuilt from transactions when database is loaded.
+    #[serde(skip_serializing, skip_deserializing)]
+    amount_index: BTreeMap<i64, PerfidbRoaringBitmap>,
+
+    /// Transactions without any label, for fast `label IS NULL` queries.
+    /// Not persisted, it is rebuilt from transactions when database is loaded.
+    #[serde(skip_serializing, skip_deserializing, default = "PerfidbRoaringBitmap::new")]
+    unlabelled: PerfidbRoaringBitmap,
+
     label_minhash: StringMinHash,
 
     /// label id to a list of transactions with that tag
@@ -79,11 +99,48 @@ pub(crate) struct Database {
 
     imported_md5s: HashMap<[u8; 16], String>,
 
+    /// Data added after the database file format was fixed. Saved separately after the main content, see [Extension].
+    #[serde(skip_serializing, skip_deserializing)]
+    extension: Extension,
+
     #[serde(skip_serializing, skip_deserializing)]
     file_path: Option<String>,
 
     #[serde(skip_serializing, skip_deserializing)]
     pub(crate) last_query_results: Option<Vec<u32>>,
+
+    /// The last SELECT statement, so it can be replayed
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) last_select: Option<String>,
+
+    /// Auto labelling rules, read from rules file on first use and kept until reloaded
+    #[serde(skip_serializing, skip_deserializing)]
+    labeller: Option<Rc<Labeller>>,
+
+    /// Config in rules file, read on first use and kept until reloaded
+    #[serde(skip_serializing, skip_deserializing)]
+    config: Option<Rc<Config>>,
+
+    /// If true, changes are only written to disk by `flush()`, e.g. when running `SAVE`
+    #[serde(skip_serializing, skip_deserializing)]
+    autosave_disabled: bool,
+
+    /// Whether there are changes not written to disk yet
+    #[serde(skip_serializing, skip_deserializing)]
+    dirty: bool,
+
+    /// In read-only mode all operations modifying the database are ignored
+    #[serde(skip_serializing, skip_deserializing)]
+    readonly: bool,
+
+    /// Recent query results, cleared when transactions change
+    #[serde(skip_serializing, skip_deserializing)]
+    query_cache: QueryCache,
+}
+
+/// Path of the backup file, which is the previous version of the database file
+pub(crate) fn backup_path(path_str: &str) -> String {
+    format!("{path_str}.bak")
 }
 
 impl Database {
@@ -92,13 +149,23 @@ impl Database {
             transaction_id_seed: 1,
             transactions: HashMap::new(),
             date_index: BTreeMap::new(),
+            amount_index: BTreeMap::new(),
+            unlabelled: PerfidbRoaringBitmap::new(),
             label_minhash: StringMinHash::new(),
             label_id_to_transactions: HashMap::new(),
             search_index: SearchIndex::new(),
             imported_files: HashMap::new(),
             imported_md5s: HashMap::new(),
+            extension: Extension::default(),
             file_path: Some(file_path),
             last_query_results: None,
+            last_select: None,
+            labeller: None,
+            config: None,
+            autosave_disabled: false,
+            dirty: false,
+            readonly: false,
+            query_cache: QueryCache::default(),
         }
     }
 
@@ -117,8 +184,14 @@ impl Database {
             let mut buffer: Vec<u8> = vec![];
             file.read_to_end(&mut buffer)?;
 
-            let mut database :Database = bincode::deserialize(&buffer).with_context(|| "Cannot deserialise db")?;
+            let mut reader = buffer.as_slice();
+            let mut database :Database = bincode::deserialize_from(&mut reader).with_context(|| "Cannot deserialise db")?;
+            // Database files written by older versions do not have the extension section
+            if !reader.is_empty() {
+                database.extension = Extension::from_bytes(reader).with_context(|| "Cannot deserialise db extension")?;
+            }
             database.file_path = Some(path_str.to_string());
+            database.build_indexes();
             Ok(database)
         } else {
             println!("create new db: {:?}", path_str);
@@ -126,8 +199,68 @@ impl Database {
         }
     }
 
-    /// Save db content to disk
-    pub(crate) fn save(&self) {
+    pub(crate) fn set_readonly(&mut self) {
+        self.readonly = true;
+    }
+
+    pub(crate) fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Return false and log a warning if database is read-only
+    fn check_writable(&self, operation: &str) -> bool {
+        if self.readonly {
+            warn!("Database is read-only, {operation} is ignored.");
+        }
+        !self.readonly
+    }
+
+    /// Config in the given rules file. The file is only read the first time.
+    pub(crate) fn config(&mut self, rules_file: &str) -> Rc<Config> {
+        self.config.get_or_insert_with(|| Rc::new(Config::load_from_file(rules_file))).clone()
+    }
+
+    /// Auto labeller using rules in the given file. The file is only read the first time.
+    pub(crate) fn labeller(&mut self, rules_file: &str) -> Rc<Labeller> {
+        if self.labeller.is_none() {
+            self.labeller = Some(Rc::new(Labeller::new(&self.config(rules_file))));
+        }
+        self.labeller.clone().unwrap()
+    }
+
+    /// Read rules file again, e.g. after editing auto labelling rules or config during a session
+    pub(crate) fn reload_rules(&mut self, rules_file: &str) {
+        self.config = None;
+        self.labeller = None;
+        self.labeller(rules_file);
+    }
+
+    pub(crate) fn disable_autosave(&mut self) {
+        self.autosave_disabled = true;
+    }
+
+    pub(crate) fn has_unsaved_changes(&self) -> bool {
+        self.dirty
+    }
+
+    /// Save db content to disk after a change. If autosave is disabled db is only marked as changed.
+    pub(crate) fn save(&mut self) {
+        if !self.check_writable("save") {
+            return;
+        }
+        if self.autosave_disabled {
+            self.dirty = true;
+            return;
+        }
+        self.flush();
+    }
+
+    /// Write db content to disk
+    pub(crate) fn flush(&mut self) {
+        if !self.check_writable("save") {
+            return;
+        }
+
         // Create metadata using current binary version
         let metadata = Metadata { version: PERFIDB_VERSION.to_string() };
         let metadata_encoded: Vec<u8> = bincode::serialize(&metadata).unwrap();
@@ -136,8 +269,16 @@ impl Database {
 
         let encoded: Vec<u8> = bincode::serialize(&self).unwrap();
 
+        // Keep the previous version as a backup, so it can be restored if the database file gets corrupted
+        let file_path = self.file_path.as_ref().unwrap();
+        if Path::new(file_path).is_file() {
+            if let Err(e) = fs::copy(file_path, backup_path(file_path)) {
+                warn!("Unable to back up database file: {e}");
+            }
+        }
+
         // Use first 1024 bytes to store metadata
-        let mut file = fs::File::create(self.file_path.as_ref().unwrap()).unwrap();
+        let mut file = fs::File::create(file_path).unwrap();
         // Using first 2 bytes to write metadata length
         file.write_u16::<LittleEndian>(metadata_length as u16).unwrap();
         // Write metadata
@@ -147,15 +288,52 @@ impl Database {
         file.write_all(&vec![0; remaining_header_bytes]).unwrap();
 
         file.write_all(&encoded).expect("Unable to write to database file");
+        file.write_all(&self.extension.to_bytes()).expect("Unable to write to database file");
         file.flush().unwrap();
+        self.dirty = false;
+    }
+
+    /// Build indexes which are not persisted in database file
+    fn build_indexes(&mut self) {
+        self.amount_index.clear();
+        self.unlabelled = PerfidbRoaringBitmap::new();
+        for t in self.transactions.values() {
+            self.amount_index.entry(to_cents(t.amount)).or_insert(PerfidbRoaringBitmap::new()).insert(t.id);
+            if !t.has_tags() {
+                self.unlabelled.insert(t.id);
+            }
+        }
     }
 
     pub(crate) fn file_exist(&self, file_path: &str) -> bool {
         self.imported_files.contains_key(file_path)
     }
 
+    /// Return the path of the imported file with the given md5, if any
+    pub(crate) fn file_with_md5(&self, md5: &Digest) -> Option<&String> {
+        self.imported_md5s.get(&md5.0)
+    }
+
+    /// Save a SELECT statement under the given name, replacing the existing view with the same name.
+    /// This function DOES NOT save db.
+    pub(crate) fn create_view(&mut self, name: &str, sql: &str) {
+        if !self.check_writable("creating view") {
+            return;
+        }
+        self.extension.views.insert(name.to_lowercase(), sql.to_string());
+    }
+
+    /// SELECT statement saved under the given name
+    pub(crate) fn view(&self, name: &str) -> Option<&String> {
+        self.extension.views.get(&name.to_lowercase())
+    }
+
     /// Record a file has been imported and the file's md5
     pub(crate) fn record_file_md5(&mut self, file_path: &str, md5: Digest) -> anyhow::Result<Option<Digest>> {
+        if !self.check_writable("recording imported file") {
+            return Ok(None);
+        }
+
         match self.imported_files.entry(file_path.to_string()) {
             Entry::Occupied(mut existing) => {
                 let old_md5 = existing.insert(md5.0);
@@ -173,7 +351,58 @@ impl Database {
         }
     }
 
-    pub(crate) fn upsert(&mut self, t: &Record) {
+    /// Record the statement file the transactions were imported from.
+    /// This function DOES NOT save db.
+    pub(crate) fn record_source_file(&mut self, file_path: &str, trans_ids: &[u32]) {
+        if !self.check_writable("recording source file") {
+            return;
+        }
+        for trans_id in trans_ids {
+            self.extension.source_files.insert(*trans_id, file_path.to_string());
+        }
+    }
+
+    /// Record files imported by an IMPORT statement, replacing the previous import batch.
+    /// This function DOES NOT save db.
+    pub(crate) fn set_last_import(&mut self, files: Vec<String>) {
+        if !self.check_writable("recording import batch") {
+            return;
+        }
+        self.extension.last_import = files;
+    }
+
+    /// Delete transactions imported by the last IMPORT statement, and forget the imported files so they can be
+    /// imported again. Returns the files and ids of deleted transactions.
+    /// This function DOES NOT save db.
+    pub(crate) fn rollback_last_import(&mut self) -> (Vec<String>, Vec<u32>) {
+        if !self.check_writable("rollback") {
+            return (vec![], vec![]);
+        }
+
+        let files = std::mem::take(&mut self.extension.last_import);
+        let mut trans_ids: Vec<u32> = self.extension.source_files.iter()
+            .filter(|(_, f)| files.contains(f))
+            .map(|(id, _)| *id)
+            .collect();
+        trans_ids.sort();
+        for trans_id in &trans_ids {
+            self.delete_single(*trans_id);
+        }
+        for f in &files {
+            if let Some(md5) = self.imported_files.remove(f) {
+                self.imported_md5s.remove(&md5);
+            }
+        }
+        (files, trans_ids)
+    }
+
+    /// Insert or overwrite a transaction. Returns the transaction id, or None if the database is read-only.
+    pub(crate) fn upsert(&mut self, t: &Record) -> Option<u32> {
+        if !self.check_writable("upsert") {
+            return None;
+        }
+        self.query_cache.clear();
+
         let trans_id = match t.id {
             Some(id) => id,
             None => self.transaction_id_seed
@@ -189,6 +418,12 @@ impl Database {
         // Add to date index
         self.date_index.entry(date).or_insert(PerfidbRoaringBitmap::new()).insert(trans_id);
 
+        // Add to amount index, removing the old amount if we are overwriting an existing transaction
+        if let Some(existing) = self.transactions.get(&trans_id) {
+            self.amount_index.entry(to_cents(existing.amount)).and_modify(|bitmap| { bitmap.remove(trans_id); });
+        }
+        self.amount_index.entry(to_cents(t.amount)).or_insert(PerfidbRoaringBitmap::new()).insert(trans_id);
+
         let label_ids = match &t.labels {
             Some(labels) => {
                 let label_ids: Vec<u32> = labels.iter().map(|l| self.label_minhash.put(l)).collect();
@@ -202,6 +437,26 @@ impl Database {
             self.label_id_to_transactions.entry(*label_id).or_insert(PerfidbRoaringBitmap::new())
                 .insert(trans_id);
         }
+        if label_ids.is_empty() {
+            self.unlabelled.insert(trans_id);
+        } else {
+            self.unlabelled.remove(trans_id);
+        }
+
+        match &t.currency {
+            Some(currency) => self.extension.currencies.insert(trans_id, currency.clone()),
+            None => self.extension.currencies.remove(&trans_id),
+        };
+        match t.balance {
+            Some(balance) => self.extension.balances.insert(trans_id, balance),
+            None => self.extension.balances.remove(&trans_id),
+        };
+        match t.raw_amount {
+            Some(raw_amount) => self.extension.raw_amounts.insert(trans_id, raw_amount),
+            None => self.extension.raw_amounts.remove(&trans_id),
+        };
+        // Updating an existing transaction keeps its original import time
+        self.extension.imported_at.entry(trans_id).or_insert_with(|| Local::now().naive_local());
 
         let t = TransactionRecord {
             id: trans_id,
@@ -215,10 +470,19 @@ impl Database {
 
         // Add to transactions table
         self.transactions.insert(trans_id, t);
+        Some(trans_id)
     }
 
-    /// Applying labelling operations on a transaction
-    pub(crate) fn apply_label_ops(&mut self, trans_id: u32, label_cmd: LabelCommand, auto_label_rules_file: &str) {
+    /// Applying labelling operations on a transaction.
+    /// The labeller is only used by auto labelling, callers should build it once and reuse it across transactions.
+    /// This function DOES NOT save db, so labelling many transactions only rewrites the database file once.
+    /// save() must be explicitly called after all label operations are applied.
+    pub(crate) fn apply_label_ops(&mut self, trans_id: u32, label_cmd: LabelCommand, labeller: &Labeller) {
+        if !self.check_writable("labelling") {
+            return;
+        }
+        self.query_cache.clear();
+
         match label_cmd {
             LabelCommand::Manual(label_ops) => {
                 for op in label_ops {
@@ -243,175 +507,470 @@ impl Database {
                         }
                     });
                 }
+
+                if let Some(transaction) = self.transactions.get(&trans_id) {
+                    if transaction.has_tags() {
+                        self.unlabelled.remove(trans_id);
+                    } else {
+                        self.unlabelled.insert(trans_id);
+                    }
+                }
             }
 
-            LabelCommand::Auto => {
+            LabelCommand::Auto | LabelCommand::AutoUnlabelled | LabelCommand::AutoAdd => {
                 if let Some(transaction) = self.transactions.get(&trans_id) {
+                    if label_cmd == LabelCommand::AutoUnlabelled && transaction.has_tags() {
+                        return;
+                    }
+
                     let mut label_ops: Vec<LabelOp> = vec![];
-                    for label_hash in (*transaction.labels).iter() {
-                        label_ops.push(LabelOp::new_remove(self.label_minhash.lookup_by_hash(label_hash).unwrap()));
+                    if label_cmd == LabelCommand::Auto {
+                        for label_hash in (*transaction.labels).iter() {
+                            label_ops.push(LabelOp::new_remove(self.label_minhash.lookup_by_hash(label_hash).unwrap()));
+                        }
                     }
-                    let tagger = Labeller::new(&Config::load_from_file(auto_label_rules_file));
-                    for new_label in tagger.label(&transaction.description) {
+                    for new_label in labeller.label(&transaction.description) {
                         label_ops.push(LabelOp::new_add(&new_label));
                     }
 
-                    self.apply_label_ops(trans_id, LabelCommand::Manual(label_ops), auto_label_rules_file);
+                    self.apply_label_ops(trans_id, LabelCommand::Manual(label_ops), labeller);
+                }
+            }
+        }
+    }
+
+    /// Replace source labels with the target label on all transactions, then remove the source labels.
+    /// Returns ids of transactions changed. This function DOES NOT save db.
+    pub(crate) fn merge_labels(&mut self, sources: &[String], target: &str) -> Vec<u32> {
+        if !self.check_writable("merging labels") {
+            return vec![];
+        }
+        self.query_cache.clear();
+
+        let source_ids: Vec<u32> = sources.iter().filter_map(|label| self.label_minhash.lookup_by_string(label)).collect();
+        if source_ids.is_empty() {
+            return vec![];
+        }
+
+        let target_id = self.label_minhash.put(target);
+        let mut merged = RoaringBitmap::new();
+        for source_id in source_ids {
+            if source_id == target_id {
+                continue;
+            }
+
+            if let Some(bitmap) = self.label_id_to_transactions.remove(&source_id) {
+                for trans_id in &bitmap.0 {
+                    if let Some(transaction) = self.transactions.get_mut(&trans_id) {
+                        transaction.labels.remove(source_id);
+                        transaction.labels.add(target_id);
+                    }
+                }
+                merged |= bitmap.0;
+            }
+            self.label_minhash.retain(|label_id| *label_id != source_id);
+        }
+
+        self.label_id_to_transactions.entry(target_id).or_insert(PerfidbRoaringBitmap::new()).0 |= &merged;
+        merged.iter().collect()
+    }
+
+    /// Move transactions to another account. Returns ids of transactions moved. This function DOES NOT save db.
+    pub(crate) fn move_to_account(&mut self, ids: &[u32], account: &str) -> Vec<u32> {
+        if !self.check_writable("moving transactions") {
+            return vec![];
+        }
+        self.query_cache.clear();
+
+        let mut moved = vec![];
+        for trans_id in ids {
+            // Account is not indexed, so date and label indices stay the same
+            if let Some(t) = self.transactions.get_mut(trans_id) {
+                if t.account != account {
+                    t.account = account.to_string();
+                    moved.push(*trans_id);
                 }
             }
         }
+        moved
+    }
+
+    /// Number of transactions and net amount of each account, sorted by account name
+    pub(crate) fn list_accounts(&self) -> Vec<AccountSummary> {
+        // Sum in cents so the net amount does not drift after many transactions
+        let mut accounts: BTreeMap<&str, (usize, i64)> = BTreeMap::new();
+        for t in self.transactions.values() {
+            let (count, net) = accounts.entry(t.account.as_str()).or_default();
+            *count += 1;
+            *net += to_cents(t.amount);
+        }
+        accounts.into_iter()
+            .map(|(account, (transactions, net))| AccountSummary { account: account.to_string(), transactions, net: net as f32 / 100.0 })
+            .collect()
+    }
 
-        self.save()
+    /// All transactions with the label
+    fn label_transactions(&self, label: &str) -> RoaringBitmap {
+        match self.label_minhash.lookup_by_string(label) {
+            Some(label_id) => self.label_id_to_transactions.get(&label_id).unwrap().0.clone(),
+            None => {
+                // Most likely a typo, otherwise it looks the same as a label without matching transactions
+                warn!("Label '{label}' does not exist.");
+                RoaringBitmap::new()
+            }
+        }
+    }
+
+    /// Whether the result of a condition can be cached. Conditions that log a warning, e.g. about a label that
+    /// does not exist, are not cached so the warning is shown every time the query runs.
+    fn is_cacheable(&self, condition: &Condition) -> bool {
+        let label_exists = |label: &String| self.label_minhash.lookup_by_string(label).is_some();
+        match condition {
+            Condition::Label(Operator::Eq | Operator::NotEq, label) | Condition::PrimaryLabel(_, label) => label_exists(label),
+            Condition::LabelHasAll(labels) | Condition::LabelHasAny(labels) => labels.iter().all(label_exists),
+            Condition::Description(Operator::Regex, pattern) => Regex::new(&format!("(?i){pattern}")).is_ok(),
+            Condition::And(conditions) | Condition::Or(conditions) => self.is_cacheable(&conditions.0) && self.is_cacheable(&conditions.1),
+            Condition::Not(condition) => self.is_cacheable(condition),
+            _ => true,
+        }
     }
 
     /// Filter transactions based on the given SQL where clause.
     /// Returns the set of transaction ids after applying the filter.
-    fn filter_transactions(&self, transactions: &HashSet<u32>, condition: Condition) -> HashSet<u32> {
-        let get_amount = |id| self.transactions.get(id).unwrap().amount;
-
+    fn filter_transactions(&self, transactions: &RoaringBitmap, condition: Condition) -> RoaringBitmap {
         match condition {
             Condition::Id(id) => {
-                let mut trans = HashSet::new();
-                if self.search_by_id(id).is_some() {
+                let mut trans = RoaringBitmap::new();
+                if transactions.contains(id) {
                     trans.insert(id);
                 }
 
-                transactions.intersection(&trans).cloned().collect()
+                trans
             }
 
+            Condition::IdIn(ids) => ids.into_iter().collect::<RoaringBitmap>() & transactions,
+
             Condition::Spending(op, spending) => {
-                let amount_limit = -spending;
-                match op {
-                    Operator::Gt => transactions.iter().filter(|id| get_amount(id) < amount_limit).cloned().collect::<HashSet<u32>>(),
-                    Operator::GtEq => transactions.iter().filter(|id| get_amount(id) <= amount_limit).cloned().collect::<HashSet<u32>>(),
-                    Operator::Lt => transactions.iter().filter(|id| {
-                        let amount = get_amount(id);
-                        amount > amount_limit && amount <= 0.0
-                    }).cloned().collect::<HashSet<u32>>(),
-                    Operator::LtEq => transactions.iter().filter(|id| {
-                        let amount = get_amount(id);
-                        amount >= amount_limit && amount <= 0.0
-                    }).cloned().collect::<HashSet<u32>>(),
-                    Operator::Eq => transactions.iter().filter(|id| get_amount(id) == amount_limit).cloned().collect::<HashSet<u32>>(),
-                    _ => HashSet::new(),
-                }
+                let amount_limit = -to_cents(spending);
+                let trans_in_amount_range = match op {
+                    Operator::Gt => self.amount_range(Unbounded, Excluded(amount_limit)),
+                    Operator::GtEq => self.amount_range(Unbounded, Included(amount_limit)),
+                    Operator::Lt => self.amount_range(Excluded(amount_limit), Included(0)),
+                    Operator::LtEq => self.amount_range(Included(amount_limit), Included(0)),
+                    Operator::Eq => self.amount_range(Included(amount_limit), Included(amount_limit)),
+                    _ => RoaringBitmap::new(),
+                };
+
+                trans_in_amount_range & transactions
             }
 
             Condition::Income(op, income_limit) => {
-                match op {
-                    Operator::Gt => transactions.iter().filter(|id| get_amount(id) > income_limit).cloned().collect::<HashSet<u32>>(),
-                    Operator::GtEq => transactions.iter().filter(|id| get_amount(id) >= income_limit).cloned().collect::<HashSet<u32>>(),
-                    Operator::Lt => transactions.iter().filter(|id| {
-                        let amount = get_amount(id);
-                        amount >= 0.0 && amount < income_limit
-                    }).cloned().collect::<HashSet<u32>>(),
-                    Operator::LtEq => transactions.iter().filter(|id| {
-                        let amount = get_amount(id);
-                        amount >= 0.0 && amount <= income_limit
-                    }).cloned().collect::<HashSet<u32>>(),
-                    Operator::Eq => transactions.iter().filter(|id| get_amount(id) == income_limit).cloned().collect::<HashSet<u32>>(),
-                    _ => HashSet::new(),
-                }
+                let income_limit = to_cents(income_limit);
+                let trans_in_amount_range = match op {
+                    Operator::Gt => self.amount_range(Excluded(income_limit), Unbounded),
+                    Operator::GtEq => self.amount_range(Included(income_limit), Unbounded),
+                    Operator::Lt => self.amount_range(Included(0), Excluded(income_limit)),
+                    Operator::LtEq => self.amount_range(Included(0), Included(income_limit)),
+                    Operator::Eq => self.amount_range(Included(income_limit), Included(income_limit)),
+                    _ => RoaringBitmap::new(),
+                };
+
+                trans_in_amount_range & transactions
             }
 
             Condition::Amount(op, amount_limit) => {
-                match op {
-                    Operator::Gt => transactions.iter().filter(|id| get_amount(id) > amount_limit).cloned().collect::<HashSet<u32>>(),
-                    Operator::GtEq => transactions.iter().filter(|id| get_amount(id) >= amount_limit).cloned().collect::<HashSet<u32>>(),
-                    Operator::Lt => transactions.iter().filter(|id| get_amount(id) < amount_limit).cloned().collect::<HashSet<u32>>(),
-                    Operator::LtEq => transactions.iter().filter(|id| get_amount(id) <= amount_limit).cloned().collect::<HashSet<u32>>(),
-                    Operator::Eq => transactions.iter().filter(|id| get_amount(id) == amount_limit).cloned().collect::<HashSet<u32>>(),
-                    _ => HashSet::new(),
+                let amount_limit = to_cents(amount_limit);
+                let trans_in_amount_range = match op {
+                    Operator::Gt => self.amount_range(Excluded(amount_limit), Unbounded),
+                    Operator::GtEq => self.amount_range(Included(amount_limit), Unbounded),
+                    Operator::Lt => self.amount_range(Unbounded, Excluded(amount_limit)),
+                    Operator::LtEq => self.amount_range(Unbounded, Included(amount_limit)),
+                    Operator::Eq => self.amount_range(Included(amount_limit), Included(amount_limit)),
+                    _ => RoaringBitmap::new(),
+                };
+
+                trans_in_amount_range & transactions
+            }
+
+            // Whole dollar amounts have no cents, or amounts with cents if `whole` is false
+            Condition::WholeAmount(whole) => {
+                let mut matched = RoaringBitmap::new();
+                for (_, trans_ids) in self.amount_index.iter().filter(|(cents, _)| (*cents % 100 == 0) == whole) {
+                    matched |= &trans_ids.0;
                 }
+                matched & transactions
+            }
+
+            // Union of the positive and negative amount ranges
+            Condition::AbsAmount(op, amount_limit) => {
+                let amount_limit = to_cents(amount_limit.abs());
+                let trans_in_amount_range = match op {
+                    Operator::Gt => self.amount_range(Excluded(amount_limit), Unbounded) | self.amount_range(Unbounded, Excluded(-amount_limit)),
+                    Operator::GtEq => self.amount_range(Included(amount_limit), Unbounded) | self.amount_range(Unbounded, Included(-amount_limit)),
+                    Operator::Lt => self.amount_range(Excluded(-amount_limit), Excluded(amount_limit)),
+                    Operator::LtEq => self.amount_range(Included(-amount_limit), Included(amount_limit)),
+                    Operator::Eq => self.amount_range(Included(amount_limit), Included(amount_limit)) | self.amount_range(Included(-amount_limit), Included(-amount_limit)),
+                    _ => RoaringBitmap::new(),
+                };
+
+                trans_in_amount_range & transactions
+            }
+
+            Condition::Description(Operator::Regex, pattern) => {
+                // Case-insensitive, same as auto labelling rules
+                match Regex::new(&format!("(?i){pattern}")) {
+                    Ok(regex) => transactions.iter().filter(|id| regex.is_match(&self.transactions.get(id).unwrap().description)).collect(),
+                    Err(e) => {
+                        warn!("Invalid regex '{pattern}': {e}");
+                        RoaringBitmap::new()
+                    }
+                }
+            }
+
+            // Whole description, case-insensitive
+            Condition::Description(Operator::Eq, text) => {
+                let text = text.to_lowercase();
+                transactions.iter().filter(|id| self.transactions.get(id).unwrap().description.to_lowercase() == text).collect()
+            }
+
+            // Case-insensitive, same as full text search
+            Condition::Description(op @ (Operator::StartsWith | Operator::EndsWith), text) => {
+                let text = text.to_lowercase();
+                transactions.iter().filter(|id| {
+                    let description = self.transactions.get(id).unwrap().description.to_lowercase();
+                    match op {
+                        Operator::StartsWith => description.starts_with(&text),
+                        _ => description.ends_with(&text),
+                    }
+                }).collect()
             }
 
             // Assuming op is 'Match' for now
             Condition::Description(_op, keyword) => {
-                self.search_index.search(&keyword)
+                self.search_index.search(&keyword) & transactions
+            }
+
+            // Union of transactions matching each text
+            Condition::DescriptionAny(texts) => {
+                let mut matched = RoaringBitmap::new();
+                for text in texts {
+                    matched |= self.search_index.search(&text);
+                }
+                matched & transactions
             }
 
             Condition::Label(op, label) => {
-                let trans_with_label = match op {
-                    Operator::Eq => {
-                        match self.label_minhash.lookup_by_string(label) {
-                            Some(label_id) => self.label_id_to_transactions.get(&label_id).unwrap().iter().collect::<HashSet<u32>>(),
-                            None => HashSet::new()
-                        }
-                    }
+                match op {
+                    Operator::Eq => self.label_transactions(&label) & transactions,
 
                     Operator::NotEq => {
-                        let mut all_trans :HashSet<u32> = self.transactions.keys().map(|k| *k).collect();
-
-                        if let Some(label_id) = self.label_minhash.lookup_by_string(label) {
+                        match self.label_minhash.lookup_by_string(&label) {
                             // remove the transaction with this label, the remaining will be != label
-                            for trans_id in self.label_id_to_transactions.get(&label_id).unwrap().iter() {
-                                all_trans.remove(&trans_id);
+                            Some(label_id) => transactions - &self.label_id_to_transactions.get(&label_id).unwrap().0,
+                            None => {
+                                warn!("Label '{label}' does not exist.");
+                                transactions.clone()
                             }
                         }
-
-                        all_trans
                     }
 
-                    Operator::IsNull => {
-                        transactions.iter().filter(|id| !self.transactions.get(id).unwrap().has_tags()).cloned().collect::<HashSet<u32>>()
+                    // Union of all labels containing the keyword
+                    Operator::Match => {
+                        let mut matched = RoaringBitmap::new();
+                        for label_id in self.label_minhash.lookup_containing(&label) {
+                            if let Some(trans_ids) = self.label_id_to_transactions.get(&label_id) {
+                                matched |= &trans_ids.0;
+                            }
+                        }
+                        matched & transactions
                     }
 
-                    Operator::IsNotNull => {
-                        transactions.iter().filter(|id| self.transactions.get(id).unwrap().has_tags()).cloned().collect::<HashSet<u32>>()
+                    Operator::IsNull => &self.unlabelled.0 & transactions,
+
+                    Operator::IsNotNull => transactions - &self.unlabelled.0,
+
+                    _ => RoaringBitmap::new()
+                }
+            }
+
+            // Unlabelled transactions don't have a primary label, so they only match '!='
+            Condition::PrimaryLabel(op, label) => {
+                match self.label_minhash.lookup_by_string(&label) {
+                    Some(label_id) => transactions.iter()
+                        .filter(|id| (self.transactions.get(id).unwrap().labels.first() == Some(&label_id)) == (op == Operator::Eq))
+                        .collect(),
+                    None => {
+                        warn!("Label '{label}' does not exist.");
+                        match op {
+                            Operator::NotEq => transactions.clone(),
+                            _ => RoaringBitmap::new()
+                        }
                     }
+                }
+            }
 
-                    _ => HashSet::new()
+            Condition::LabelHasAll(labels) => {
+                let mut matched = transactions.clone();
+                for label in labels {
+                    matched &= self.label_transactions(&label);
+                }
+                matched
+            }
+
+            Condition::LabelHasAny(labels) => {
+                let mut matched = RoaringBitmap::new();
+                for label in labels {
+                    matched |= self.label_transactions(&label);
+                }
+                matched & transactions
+            }
+
+            Condition::Account(op, account) => {
+                let is_eq = op == Operator::Eq;
+                transactions.iter().filter(|id| (self.transactions.get(id).unwrap().account == account) == is_eq).collect()
+            }
+
+            Condition::PercentOfMax(kind, op, percent) => {
+                let magnitude = |id: u32| {
+                    let amount = self.transactions.get(&id).unwrap().amount;
+                    match kind {
+                        AmountKind::Spending if amount < 0.0 => Some(-amount),
+                        AmountKind::Income if amount > 0.0 => Some(amount),
+                        AmountKind::Amount => Some(amount.abs()),
+                        _ => None
+                    }
                 };
 
-                transactions.intersection(&trans_with_label).cloned().collect::<HashSet<u32>>()
+                let max = transactions.iter().filter_map(magnitude).fold(0.0, f32::max);
+                let threshold = max * percent / 100.0;
+                transactions.iter().filter(|id| match magnitude(*id) {
+                    Some(m) => match op {
+                        Operator::Gt => m > threshold,
+                        Operator::GtEq => m >= threshold,
+                        Operator::Lt => m < threshold,
+                        Operator::LtEq => m <= threshold,
+                        _ => false
+                    },
+                    None => false
+                }).collect()
+            }
+
+            Condition::TagCount(op, count) => {
+                transactions.iter().filter(|id| compare_count(&op, self.transactions.get(id).unwrap().labels.len(), count)).collect()
+            }
+
+            // Number of characters rather than bytes, so accented letters count as one
+            Condition::DescriptionLength(op, length) => {
+                transactions.iter().filter(|id| compare_count(&op, self.transactions.get(id).unwrap().description.chars().count(), length)).collect()
             }
 
-            Condition::Date(_op, date_range) => {
-                let mut trans_in_date_range = HashSet::<u32>::new();
+            // Date comparisons, e.g. 'date >= 2023-01-01', are already parsed into open-ended ranges
+            Condition::Date(op, date_range) => {
+                let mut trans_in_date_range = RoaringBitmap::new();
                 for (_, trans_ids) in self.date_index.range(date_range) {
-                    for id in trans_ids.iter() {
-                        trans_in_date_range.insert(id);
-                    }
+                    trans_in_date_range |= &trans_ids.0;
+                }
+
+                match op {
+                    Operator::NotEq => transactions - trans_in_date_range,
+                    _ => trans_in_date_range & transactions
                 }
+            }
 
-                transactions.intersection(&trans_in_date_range).cloned().collect::<HashSet<u32>>()
+            // Transactions without import time, i.e. added by older versions, never match
+            Condition::Imported(op, date_range) => {
+                transactions.iter().filter(|id| match self.extension.imported_at.get(id) {
+                    Some(imported_at) => date_range.contains(&imported_at.date()) != (op == Operator::NotEq),
+                    None => false,
+                }).collect()
             }
 
+            // E.g. for 'NOT month = 12', transactions in the date index range of December are subtracted
+            Condition::Not(condition) => transactions - self.filter_transactions(transactions, *condition),
+
             Condition::And(sub_conditions) => {
-                let c1_result = self.filter_transactions(transactions, (*sub_conditions).0);
-                let c2_result = self.filter_transactions(transactions, (*sub_conditions).1);
-                c1_result.intersection(&c2_result).cloned().collect::<HashSet<u32>>().intersection(&transactions).cloned().collect()
+                // Only need to apply the second condition on results of the first one
+                let c1_result = self.filter_transactions(transactions, sub_conditions.0);
+                self.filter_transactions(&c1_result, sub_conditions.1)
             }
 
             Condition::Or(sub_conditions) => {
-                let c1_result = self.filter_transactions(transactions, (*sub_conditions).0);
-                let c2_result = self.filter_transactions(transactions, (*sub_conditions).1);
-                c1_result.union(&c2_result).cloned().collect::<HashSet<u32>>().intersection(&transactions).cloned().collect()
+                let c1_result = self.filter_transactions(transactions, sub_conditions.0);
+                let c2_result = self.filter_transactions(transactions, sub_conditions.1);
+                c1_result | c2_result
             }
         }
     }
 
-    /// The new select implementation
-    pub(crate) fn query(&mut self, from: Option<String>, condition: Option<Condition>, order_by: OrderBy, limit: Option<usize>) -> Vec<Transaction> {
-        let mut trans :HashSet<u32> = match from {
-            None => self.transactions.keys().cloned().collect::<HashSet<u32>>(),
-            Some(account) => self.transactions.values().filter(|t| account == t.account).map(|t| t.id).collect()
+    /// Return all transactions with amount (in cents) within the given bounds
+    fn amount_range(&self, start: Bound<i64>, end: Bound<i64>) -> RoaringBitmap {
+        let start = match start {
+            Included(cents) => cents,
+            Excluded(cents) => cents + 1,
+            Unbounded => i64::MIN,
+        };
+        let end = match end {
+            Included(cents) => cents,
+            Excluded(cents) => cents - 1,
+            Unbounded => i64::MAX,
+        };
+
+        let mut trans_in_amount_range = RoaringBitmap::new();
+        // BTreeMap::range() panics if start > end
+        if start <= end {
+            for (_, trans_ids) in self.amount_index.range(start..=end) {
+                trans_in_amount_range |= &trans_ids.0;
+            }
+        }
+        trans_in_amount_range
+    }
+
+    /// Ids of transactions in the accounts, all accounts if `from` is `None`, matching the condition
+    fn filter(&self, from: Option<Vec<String>>, condition: Option<Condition>) -> RoaringBitmap {
+        let mut trans :RoaringBitmap = match from {
+            None => self.transactions.keys().cloned().collect(),
+            Some(accounts) => self.transactions.values().filter(|t| accounts.contains(&t.account)).map(|t| t.id).collect()
         };
 
         if let Some(condition) = condition {
-            trans = self.filter_transactions(&trans, condition);
+            // Conditions relative to the max amount depend on the whole result set, so they are applied last
+            let mut relative_conditions = vec![];
+            if let Some(condition) = split_percent_of_max(condition, &mut relative_conditions) {
+                trans = self.filter_transactions(&trans, condition);
+            }
+            for condition in relative_conditions {
+                trans = self.filter_transactions(&trans, condition);
+            }
         }
+        trans
+    }
 
-        let mut trans :Vec<&TransactionRecord> = trans.iter().map(|id| self.transactions.get(id).unwrap()).collect();
+    /// The new select implementation
+    pub(crate) fn query(&mut self, from: Option<Vec<String>>, condition: Option<Condition>, order_by: OrderBy, limit: Option<usize>) -> Vec<Transaction> {
+        let cache_key = query_cache_key(&from, &condition);
+        let trans = match self.query_cache.get(&cache_key) {
+            Some(trans) => trans,
+            None => {
+                let cacheable = condition.as_ref().is_none_or(|condition| self.is_cacheable(condition));
+                let trans = self.filter(from, condition);
+                if cacheable {
+                    self.query_cache.put(cache_key, trans.clone());
+                }
+                trans
+            }
+        };
+
+        let mut trans :Vec<&TransactionRecord> = trans.iter().map(|id| self.transactions.get(&id).unwrap()).collect();
         match order_by.field {
             OrderByField::Date => {
                 trans.sort_by(|a, b| {
-                    a.date.partial_cmp(&b.date).unwrap().then(a.id.partial_cmp(&b.id).unwrap())
+                    a.date.cmp(&b.date).then(a.id.cmp(&b.id))
                 });
             }
             OrderByField::Amount => {
+                // total_cmp doesn't panic on NaN, which sorts after all other amounts
                 trans.sort_by(|a, b| {
-                    a.amount.partial_cmp(&b.amount).unwrap().then(a.id.partial_cmp(&b.id).unwrap())
+                    a.amount.total_cmp(&b.amount).then(a.id.cmp(&b.id))
                 });
             }
         }
@@ -434,33 +993,151 @@ impl Database {
         results
     }
 
+    /// Find transactions with descriptions similar to the given transaction, most similar first.
+    /// Similarity is the Jaccard index of description tokens. Returns an empty list if the transaction is not found.
+    pub(crate) fn similar(&mut self, trans_id: u32, limit: usize) -> Vec<(Transaction, f32)> {
+        let scores = self.similarity_scores(trans_id, false, limit);
+        if !scores.is_empty() {
+            self.last_query_results = Some(scores.iter().map(|(id, _)| *id).collect());
+        }
+        scores.into_iter().map(|(id, score)| (self.find_by_id(id), score)).collect()
+    }
+
+    /// Suggest labels for a transaction by majority vote of the most similar labelled transactions.
+    /// Returns the suggested labels with their votes, most voted first, and number of similar transactions that voted.
+    pub(crate) fn suggest_labels(&self, trans_id: u32) -> (Vec<(String, usize)>, usize) {
+        let neighbours = self.similarity_scores(trans_id, true, SUGGEST_LABELS_NEIGHBOURS);
+        let mut votes: HashMap<u32, usize> = HashMap::new();
+        for (id, _) in &neighbours {
+            for label_id in &*self.transactions.get(id).unwrap().labels {
+                *votes.entry(*label_id).or_insert(0) += 1;
+            }
+        }
+
+        let mut suggested: Vec<(String, usize)> = votes.into_iter()
+            .filter(|(_, count)| count * 2 > neighbours.len())
+            .map(|(label_id, count)| (self.label_minhash.lookup_by_hash(&label_id).unwrap().clone(), count))
+            .collect();
+        suggested.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        (suggested, neighbours.len())
+    }
+
+    /// Jaccard index of description tokens between the given transaction and other transactions, highest first.
+    fn similarity_scores(&self, trans_id: u32, labelled_only: bool, limit: usize) -> Vec<(u32, f32)> {
+        let target_tokens = match self.transactions.get(&trans_id) {
+            Some(t) => self.search_index.token_ids(&t.description),
+            None => return vec![]
+        };
+
+        // Only transactions sharing at least one token can be similar
+        let mut candidates = self.search_index.search_any(&target_tokens);
+        candidates.remove(trans_id);
+        if labelled_only {
+            candidates -= &self.unlabelled.0;
+        }
+
+        let mut scores: Vec<(u32, f32)> = candidates.iter().map(|id| {
+            let tokens = self.search_index.token_ids(&self.transactions.get(&id).unwrap().description);
+            let intersection = target_tokens.intersection(&tokens).count();
+            let union = target_tokens.union(&tokens).count();
+            (id, intersection as f32 / union as f32)
+        }).collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        scores.truncate(limit);
+        scores
+    }
+
+    /// All account names, sorted
+    pub(crate) fn accounts(&self) -> Vec<String> {
+        let accounts: BTreeSet<&String> = self.transactions.values().map(|t| &t.account).collect();
+        accounts.into_iter().cloned().collect()
+    }
+
+    /// All labels currently applied to at least one transaction, sorted
+    pub(crate) fn labels(&self) -> Vec<String> {
+        let labels: BTreeSet<&String> = self.label_id_to_transactions.iter()
+            .filter(|(_, trans_ids)| !trans_ids.0.is_empty())
+            .filter_map(|(label_id, _)| self.label_minhash.lookup_by_hash(label_id))
+            .collect();
+        labels.into_iter().cloned().collect()
+    }
+
     pub(crate) fn find_by_id(&self, id: u32) -> Transaction {
         let t = self.transactions.get(&id).unwrap();
         self.to_transaction(t)
     }
 
+    /// Content hash of every transaction, mapped to the transaction id. See `transaction_hash`.
+    pub(crate) fn transaction_hashes(&self) -> HashMap<u64, u32> {
+        self.transactions.values().map(|t| (transaction_hash(t.date, &t.description, t.amount), t.id)).collect()
+    }
+
     pub(crate) fn search_by_id(&self, id: u32) -> Option<Transaction> {
         self.transactions.get(&id).map(|t| self.to_transaction(t))
     }
 
-    pub(crate) fn delete(&mut self, ids: &[u32]) -> u32 {
-        let mut trans_deleted: u32 = 0;
+    /// Delete transactions and save db. Returns ids of the deleted transactions, ids not in db are skipped.
+    pub(crate) fn delete(&mut self, ids: &[u32]) -> Vec<u32> {
+        if !self.check_writable("delete") {
+            return vec![];
+        }
+
+        let mut deleted = vec![];
         for trans_id in ids {
             if self.delete_single(*trans_id) {
-                trans_deleted += 1;
+                deleted.push(*trans_id);
             }
         }
         self.save();
-        trans_deleted
+        deleted
+    }
+
+    /// Remove index entries left empty by deleting transactions and labels, so they are not written to database file.
+    /// This function DOES NOT save db.
+    pub(crate) fn compact(&mut self) -> CompactStats {
+        if !self.check_writable("compact") {
+            return CompactStats { bytes_reclaimed: 0, entries_removed: 0 };
+        }
+
+        let bytes_before = bincode::serialized_size(&self).unwrap();
+
+        let before = self.label_id_to_transactions.len();
+        self.label_id_to_transactions.retain(|_, bitmap| !bitmap.0.is_empty());
+        let empty_labels = before - self.label_id_to_transactions.len();
+        let unused_labels = self.label_minhash.retain(|label_id| self.label_id_to_transactions.contains_key(label_id));
+
+        let before = self.date_index.len();
+        self.date_index.retain(|_, bitmap| !bitmap.0.is_empty());
+        let empty_dates = before - self.date_index.len();
+        self.amount_index.retain(|_, bitmap| !bitmap.0.is_empty());
+
+        let empty_postings = self.search_index.compact();
+
+        let bytes_after = bincode::serialized_size(&self).unwrap();
+        CompactStats {
+            bytes_reclaimed: bytes_before.saturating_sub(bytes_after),
+            entries_removed: empty_labels + unused_labels + empty_dates + empty_postings,
+        }
     }
 
     /// Delete a single transaction. Return true if transaction is found and deleted.
     /// This function DOES NOT save db. save() must be explicitly called to persist the delete.
     fn delete_single(&mut self, trans_id: u32) -> bool {
         if let Some(t) = self.transactions.remove(&trans_id) {
+            self.query_cache.clear();
+
             // Remove transaction from date index
             self.date_index.entry(t.date.date()).and_modify(|bitmap| { bitmap.remove(trans_id); });
 
+            // Remove transaction from amount index
+            self.amount_index.entry(to_cents(t.amount)).and_modify(|bitmap| { bitmap.remove(trans_id); });
+            self.unlabelled.remove(trans_id);
+            self.extension.currencies.remove(&trans_id);
+            self.extension.balances.remove(&trans_id);
+            self.extension.raw_amounts.remove(&trans_id);
+            self.extension.imported_at.remove(&trans_id);
+            self.extension.source_files.remove(&trans_id);
+
             // Remove transaction from label index
             for label_id in &*t.labels {
                 self.label_id_to_transactions.entry(*label_id).and_modify(|bitmap| { bitmap.remove(trans_id); });
@@ -469,6 +1146,11 @@ impl Database {
             // Remove transaction from full text search index
             self.search_index.delete(trans_id, &t.description);
 
+            // Remove transaction from last query results so live mode does not load a deleted transaction
+            if let Some(last_query_results) = &mut self.last_query_results {
+                last_query_results.retain(|id| *id != trans_id);
+            }
+
             true
         } else {
             false
@@ -477,11 +1159,90 @@ impl Database {
 
     fn to_transaction(&self, t: &TransactionRecord) -> Transaction {
         // TODO: use a function to format tags
-        Transaction::new(t.id, t.account.clone(), t.date, t.description.as_str(), t.amount,
-                         t.labels.iter().map(|tag_id| self.label_minhash.lookup_by_hash(tag_id).unwrap().clone()).collect::<Vec<String>>())
+        let mut transaction = Transaction::new(t.id, t.account.clone(), t.date, t.description.as_str(), t.amount,
+                         t.labels.iter().map(|tag_id| self.label_minhash.lookup_by_hash(tag_id).unwrap().clone()).collect::<Vec<String>>());
+        transaction.currency = self.extension.currencies.get(&t.id).cloned();
+        transaction.balance = self.extension.balances.get(&t.id).cloned();
+        transaction.raw_amount = self.extension.raw_amounts.get(&t.id).cloned();
+        transaction
     }
 }
 
+/// Result of [Database::compact]
+#[derive(Debug)]
+pub(crate) struct CompactStats {
+    pub(crate) bytes_reclaimed: u64,
+    pub(crate) entries_removed: usize,
+}
+
+/// Result of [Database::list_accounts]
+#[derive(Debug, PartialEq)]
+pub(crate) struct AccountSummary {
+    pub(crate) account: String,
+    pub(crate) transactions: usize,
+    /// Income minus spending
+    pub(crate) net: f32,
+}
+
+/// Move conditions relative to the max amount out of the top level AND conditions, and return the remaining condition
+fn split_percent_of_max(condition: Condition, relative_conditions: &mut Vec<Condition>) -> Option<Condition> {
+    match condition {
+        Condition::PercentOfMax(..) => {
+            relative_conditions.push(condition);
+            None
+        },
+        Condition::And(sub_conditions) => {
+            let (c1, c2) = *sub_conditions;
+            match (split_percent_of_max(c1, relative_conditions), split_percent_of_max(c2, relative_conditions)) {
+                (Some(c1), Some(c2)) => Some(Condition::And(Box::new((c1, c2)))),
+                (c, None) | (None, c) => c,
+            }
+        },
+        _ => Some(condition)
+    }
+}
+
+/// Compare a count, e.g. number of labels, with a comparison operator
+fn compare_count(op: &Operator, count: usize, limit: usize) -> bool {
+    match op {
+        Operator::Eq => count == limit,
+        Operator::NotEq => count != limit,
+        Operator::Gt => count > limit,
+        Operator::GtEq => count >= limit,
+        Operator::Lt => count < limit,
+        Operator::LtEq => count <= limit,
+        _ => false
+    }
+}
+
+/// Key of query results in query cache. Relative dates, e.g. 'month = 7', are already resolved by the parser,
+/// so the same key always has the same result.
+fn query_cache_key(from: &Option<Vec<String>>, condition: &Option<Condition>) -> String {
+    format!("{from:?} {condition:?}")
+}
+
+/// Convert an amount to cents, which is used as the key of amount index. Rounding to cents means
+/// `amount = 29.95` matches regardless of f32 imprecision.
+fn to_cents(amount: f32) -> i64 {
+    (amount as f64 * 100.0).round() as i64
+}
+
+/// Create an in-memory database with a few transactions for testing queries
+#[cfg(test)]
+pub(crate) fn test_db() -> Database {
+    let mut db = Database::new("test.db".to_string());
+    let records = [
+        Record::new("cba", "2023-01-05", "Woolworths Sydney", -50.0).with_labels(&["grocery"]),
+        Record::new("cba", "2023-01-20", "Salary", 3000.0),
+        Record::new("amex", "2023-02-03", "Coles Sydney", -80.5).with_labels(&["grocery", "food"]),
+        Record::new("amex", "2023-02-14", "Restaurant", -120.0).with_labels(&["food"]),
+    ];
+    for record in &records {
+        db.upsert(record);
+    }
+    db
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -502,4 +1263,392 @@ mod tests {
         let s = serde_json::to_string::<TransactionRecord>(&t).unwrap();
         println!("{}", s);
     }
+
+    fn query_ids(db: &mut Database, from: Option<&str>, condition: Option<Condition>) -> Vec<u32> {
+        db.query(from.map(|account| vec![account.to_string()]), condition, OrderBy::date(), None).iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn test_query() {
+        let mut db = test_db();
+        assert_eq!(query_ids(&mut db, None, None), vec![1, 2, 3, 4]);
+        assert_eq!(query_ids(&mut db, Some("amex"), None), vec![3, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Id(2))), vec![2]);
+        assert_eq!(query_ids(&mut db, Some("amex"), Some(Condition::Id(2))), Vec::<u32>::new());
+        assert_eq!(query_ids(&mut db, Some("amex"), Some(Condition::IdIn(vec![2, 4, 3, 100]))), vec![3, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Spending(Operator::Gt, 60.0))), vec![3, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Income(Operator::GtEq, 0.0))), vec![2]);
+        assert_eq!(query_ids(&mut db, Some("cba"), Some(Condition::Description(Operator::Match, "sydney".into()))), vec![1]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::DescriptionAny(vec!["coles".into(), "restaurant".into(), "uber".into()]))), vec![3, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::Eq, "grocery".into()))), vec![1, 3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::NotEq, "grocery".into()))), vec![2, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::IsNull, "".into()))), vec![2]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::Match, "O".into()))), vec![1, 3, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::Match, "foo".into()))), vec![3, 4]);
+
+        // Transaction 3 is labelled 'grocery' then 'food'
+        assert_eq!(query_ids(&mut db, None, Some(Condition::PrimaryLabel(Operator::Eq, "food".into()))), vec![4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::PrimaryLabel(Operator::NotEq, "grocery".into()))), vec![2, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::PrimaryLabel(Operator::Eq, "typo".into()))), Vec::<u32>::new());
+
+        let february = NaiveDate::from_ymd_opt(2023, 2, 1).unwrap()..NaiveDate::from_ymd_opt(2023, 3, 1).unwrap();
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Date(Operator::Eq, february.clone()))), vec![3, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Date(Operator::NotEq, february.clone()))), vec![1, 2]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Not(Box::new(Condition::Date(Operator::Eq, february.clone()))))), vec![1, 2]);
+        assert_eq!(query_ids(&mut db, Some("amex"), Some(Condition::Not(Box::new(Condition::Date(Operator::NotEq, february.clone()))))), vec![3, 4]);
+        let jan_20 = NaiveDate::from_ymd_opt(2023, 1, 20).unwrap();
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Date(Operator::Eq, jan_20..jan_20.succ_opt().unwrap()))), vec![2]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Date(Operator::NotEq, jan_20..jan_20.succ_opt().unwrap()))), vec![1, 3, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Date(Operator::GtEq, jan_20..NaiveDate::MAX))), vec![2, 3, 4]);
+
+        // Import time is independent of transaction date
+        let june_1 = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap();
+        db.extension.imported_at.insert(1, june_1.and_hms_opt(10, 0, 0).unwrap());
+        db.extension.imported_at.insert(3, NaiveDate::from_ymd_opt(2023, 5, 31).unwrap().and_hms_opt(23, 59, 0).unwrap());
+        db.extension.imported_at.remove(&4);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Imported(Operator::Lt, NaiveDate::MIN..june_1))), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Imported(Operator::Eq, june_1..june_1.succ_opt().unwrap()))), vec![1]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Imported(Operator::NotEq, june_1..june_1.succ_opt().unwrap()))), vec![2, 3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Imported(Operator::GtEq, june_1..NaiveDate::MAX))), vec![1, 2]);
+
+        let and = Condition::And(Box::new((Condition::Date(Operator::Eq, february), Condition::Label(Operator::Eq, "grocery".into()))));
+        assert_eq!(query_ids(&mut db, None, Some(and)), vec![3]);
+
+        // Chaining label conditions with AND means having both labels, same as HAS ALL
+        let both = Condition::And(Box::new((Condition::Label(Operator::Eq, "grocery".into()), Condition::Label(Operator::Eq, "food".into()))));
+        assert_eq!(query_ids(&mut db, None, Some(both)), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::LabelHasAll(vec!["grocery".into(), "food".into()]))), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::LabelHasAny(vec!["grocery".into(), "food".into()]))), vec![1, 3, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::LabelHasAll(vec!["grocery".into(), "typo".into()]))), Vec::<u32>::new());
+        assert_eq!(query_ids(&mut db, Some("amex"), Some(Condition::LabelHasAny(vec!["grocery".into(), "typo".into()]))), vec![3]);
+
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Description(Operator::Regex, "^(woolworths|coles)".into()))), vec![1, 3]);
+        assert_eq!(query_ids(&mut db, Some("amex"), Some(Condition::Description(Operator::Regex, "sydney$".into()))), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Description(Operator::StartsWith, "COLES".into()))), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Description(Operator::Eq, "coles sydney".into()))), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Description(Operator::Eq, "coles".into()))), Vec::<u32>::new());
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Description(Operator::EndsWith, " sydney".into()))), vec![1, 3]);
+
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Account(Operator::Eq, "amex".into()))), vec![3, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Account(Operator::NotEq, "amex".into()))), vec![1, 2]);
+
+        assert_eq!(query_ids(&mut db, None, Some(Condition::TagCount(Operator::Gt, 1))), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::DescriptionLength(Operator::Lt, 11))), vec![2, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::DescriptionLength(Operator::Eq, 0))), Vec::<u32>::new());
+
+        // Max spending is 120.0 of all transactions, but 80.5 of transactions with 'grocery' label
+        assert_eq!(query_ids(&mut db, None, Some(Condition::PercentOfMax(AmountKind::Spending, Operator::GtEq, 50.0))), vec![3, 4]);
+        let relative = Condition::And(Box::new((
+            Condition::PercentOfMax(AmountKind::Spending, Operator::GtEq, 80.0),
+            Condition::Label(Operator::Eq, "grocery".into()))));
+        assert_eq!(query_ids(&mut db, None, Some(relative)), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::TagCount(Operator::Eq, 1))), vec![1, 4]);
+
+        let or = Condition::Or(Box::new((Condition::Id(1), Condition::Label(Operator::Eq, "food".into()))));
+        assert_eq!(query_ids(&mut db, Some("amex"), Some(or)), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_save_and_load_extension() {
+        let file_path = std::env::temp_dir().join(format!("perfidb_test_{}.db", std::process::id()));
+        let mut db = Database::new(file_path.to_str().unwrap().to_string());
+        db.upsert(&Record { currency: Some("USD".to_string()), ..Record::new("cba", "2023-01-01", "Hotel", -200.0) });
+        db.save();
+
+        db.create_view("Hotels", "SELECT * WHERE description LIKE 'hotel'");
+        db.save();
+
+        let db = Database::load(file_path.to_str().unwrap()).unwrap();
+        assert_eq!(db.find_by_id(1).currency, Some("USD".to_string()));
+        assert_eq!(db.view("hotels"), Some(&"SELECT * WHERE description LIKE 'hotel'".to_string()));
+        fs::remove_file(backup_path(file_path.to_str().unwrap())).unwrap();
+        fs::remove_file(file_path).unwrap();
+    }
+
+    #[test]
+    fn test_load_corrupt_file() {
+        let file_path = std::env::temp_dir().join(format!("perfidb_test_corrupt_{}.db", std::process::id()));
+        let file_path = file_path.to_str().unwrap();
+        let mut db = test_db();
+        db.file_path = Some(file_path.to_string());
+        db.save();
+        // Delete saves db
+        db.delete(&[1]);
+
+        // Backup is the version before the last save
+        let mut content = fs::read(file_path).unwrap();
+        content.truncate(1100);
+        fs::write(file_path, content).unwrap();
+        assert!(Database::load(file_path).is_err());
+        assert_eq!(Database::load(&backup_path(file_path)).unwrap().transactions.len(), 4);
+
+        fs::remove_file(file_path).unwrap();
+        fs::remove_file(backup_path(file_path)).unwrap();
+    }
+
+    #[test]
+    fn test_amount_eq_float_imprecision() {
+        let mut db = Database::new("test.db".to_string());
+        for (amount, description) in [("29.95", "Book"), ("-29.95", "Refund"), ("0.1", "Interest")] {
+            let trans_id = db.upsert(&Record::new("cba", "2023-01-05", description, f32::from_str(amount).unwrap()));
+            // New transactions get increasing ids
+            assert_eq!(trans_id, Some(db.transactions.len() as u32));
+        }
+
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Amount(Operator::Eq, 29.95))), vec![1]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Spending(Operator::Eq, 29.95))), vec![2]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Income(Operator::Eq, 0.1 + 0.2 - 0.2))), vec![3]);
+    }
+
+    #[test]
+    fn test_order_by_amount_with_nan() {
+        let mut db = test_db();
+        db.upsert(&Record::new("cba", "2023-01-05", "Crafted import", f32::NAN));
+
+        let order_by = OrderBy { field: OrderByField::Amount, desc: false };
+        let ids: Vec<u32> = db.query(None, None, order_by, None).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![4, 3, 1, 2, 5]);
+    }
+
+    #[test]
+    fn test_amount_index() {
+        let mut db = test_db();
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Amount(Operator::Eq, -80.5))), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::AbsAmount(Operator::Gt, 100.0))), vec![2, 4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::AbsAmount(Operator::LtEq, 80.5))), vec![1, 3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::AbsAmount(Operator::Eq, 50.0))), vec![1]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::WholeAmount(true))), vec![1, 2, 4]);
+        assert_eq!(query_ids(&mut db, Some("amex"), Some(Condition::WholeAmount(false))), vec![3]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Spending(Operator::Lt, 100.0))), vec![1, 3]);
+        // Range with start greater than end must not panic
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Spending(Operator::Lt, -10.0))), Vec::<u32>::new());
+
+        // Index is kept consistent after deleting a transaction
+        db.delete_single(3);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Amount(Operator::Eq, -80.5))), Vec::<u32>::new());
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Spending(Operator::Gt, 0.0))), vec![1, 4]);
+    }
+
+    #[test]
+    fn test_unlabelled_index() {
+        let mut db = test_db();
+        let labeller = Labeller::new(&crate::config::Config::empty());
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::IsNull, "".into()))), vec![2]);
+
+        db.apply_label_ops(2, LabelCommand::Manual(vec![LabelOp::new_add("salary")]), &labeller);
+        db.apply_label_ops(4, LabelCommand::Manual(vec![LabelOp::new_remove("food")]), &labeller);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::IsNull, "".into()))), vec![4]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::IsNotNull, "".into()))), vec![1, 2, 3]);
+
+        db.delete_single(4);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::IsNull, "".into()))), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_auto_label_modes() {
+        let mut db = test_db();
+        let config: Config = toml::from_str("[labels]\nsupermarket = \"coles|woolworths\"\nincome = \"salary\"").unwrap();
+        let labeller = Labeller::new(&config);
+        let labels = |db: &Database, id: u32| db.find_by_id(id).labels;
+
+        // Labelled transactions are skipped
+        db.apply_label_ops(1, LabelCommand::AutoUnlabelled, &labeller);
+        db.apply_label_ops(2, LabelCommand::AutoUnlabelled, &labeller);
+        assert_eq!(labels(&db, 1), vec!["grocery"]);
+        assert_eq!(labels(&db, 2), vec!["income"]);
+
+        // Existing labels are kept
+        db.apply_label_ops(3, LabelCommand::AutoAdd, &labeller);
+        assert_eq!(labels(&db, 3), vec!["grocery", "food", "supermarket"]);
+
+        // Existing labels are replaced
+        db.apply_label_ops(1, LabelCommand::Auto, &labeller);
+        assert_eq!(labels(&db, 1), vec!["supermarket"]);
+    }
+
+    #[test]
+    fn test_similar() {
+        let mut db = test_db();
+        let similar: Vec<(u32, f32)> = db.similar(1, 10).iter().map(|(t, score)| (t.id, *score)).collect();
+        // 'Woolworths Sydney' and 'Coles Sydney' share one of three tokens
+        assert_eq!(similar, vec![(3, 1.0 / 3.0)]);
+        assert_eq!(db.last_query_results, Some(vec![3]));
+        assert!(db.similar(2, 10).is_empty());
+        assert!(db.similar(100, 10).is_empty());
+    }
+
+    #[test]
+    fn test_suggest_labels() {
+        let mut db = test_db();
+        for (description, labels) in [("Coles Melbourne", vec!["grocery"]), ("Coles Express", vec!["fuel"]), ("Coles Online", vec![])] {
+            db.upsert(&Record::new("cba", "2023-03-01", description, -10.0).with_labels(&labels));
+        }
+
+        // Labelled transactions with 'coles' are 3, 5 and 6. 'grocery' has 2 votes out of 3, 'food' and 'fuel' 1 each.
+        assert_eq!(db.suggest_labels(7), (vec![("grocery".to_string(), 2)], 3));
+        assert_eq!(db.suggest_labels(2), (vec![], 0));
+    }
+
+    #[test]
+    fn test_merge_labels() {
+        let mut db = test_db();
+        // 'grocery' on 1 and 3, 'food' on 3 and 4
+        let merged = db.merge_labels(&["grocery".to_string(), "food".to_string(), "unknown".to_string()], "supermarket");
+        assert_eq!(merged, vec![1, 3, 4]);
+        assert_eq!(db.labels(), vec!["supermarket"]);
+        assert!(db.label_minhash.lookup_by_string("grocery").is_none());
+        assert_eq!(db.find_by_id(3).labels, vec!["supermarket"]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::Eq, "supermarket".into()))), vec![1, 3, 4]);
+
+        // Merging into one of the source labels keeps it
+        assert_eq!(db.merge_labels(&["Supermarket".to_string()], "supermarket"), Vec::<u32>::new());
+        assert_eq!(db.labels(), vec!["supermarket"]);
+    }
+
+    #[test]
+    fn test_move_to_account() {
+        let mut db = test_db();
+        assert_eq!(db.move_to_account(&[1, 3, 100], "westpac"), vec![1, 3]);
+        assert_eq!(db.find_by_id(3).account, "westpac");
+        assert_eq!(query_ids(&mut db, Some("westpac"), None), vec![1, 3]);
+        assert_eq!(query_ids(&mut db, Some("amex"), None), vec![4]);
+
+        // Moving to the current account is a no-op
+        assert_eq!(db.move_to_account(&[1], "westpac"), Vec::<u32>::new());
+
+        // Union of multiple accounts
+        let ids: Vec<u32> = db.query(Some(vec!["westpac".into(), "amex".into()]), None, OrderBy::date(), None).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn test_query_cache() {
+        let mut db = test_db();
+        db.disable_autosave();
+        let grocery = || Some(Condition::Label(Operator::Eq, "grocery".into()));
+        assert_eq!(query_ids(&mut db, None, grocery()), vec![1, 3]);
+        // Cached result with a different order
+        let ids: Vec<u32> = db.query(None, grocery(), OrderBy::amount_desc(), None).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+
+        // Every change to transactions invalidates cached results
+        let labeller = Labeller::new(&Config::empty());
+        db.apply_label_ops(2, LabelCommand::Manual(vec![LabelOp::new_add("grocery")]), &labeller);
+        assert_eq!(query_ids(&mut db, None, grocery()), vec![1, 2, 3]);
+        db.move_to_account(&[1], "westpac");
+        assert_eq!(query_ids(&mut db, Some("cba"), grocery()), vec![2]);
+        assert_eq!(db.delete(&[2, 100]), vec![2]);
+        assert_eq!(query_ids(&mut db, None, grocery()), vec![1, 3]);
+        db.merge_labels(&["grocery".into()], "food");
+        assert_eq!(query_ids(&mut db, None, grocery()), Vec::<u32>::new());
+
+        // Queries with unknown labels are not cached, so the warning is logged every time
+        let condition = Some(Condition::And(Box::new((Condition::Label(Operator::Eq, "food".into()), Condition::Label(Operator::Eq, "fod".into())))));
+        assert_eq!(query_ids(&mut db, None, condition.clone()), Vec::<u32>::new());
+        assert!(db.query_cache.get(&query_cache_key(&None, &condition)).is_none());
+        let food = Some(Condition::Label(Operator::Eq, "food".into()));
+        assert_eq!(query_ids(&mut db, None, food.clone()), vec![1, 3, 4]);
+        assert!(db.query_cache.get(&query_cache_key(&None, &food)).is_some());
+    }
+
+    #[test]
+    fn test_rollback_last_import() {
+        let mut db = test_db();
+        db.disable_autosave();
+        db.record_file_md5("cba/2023-01.csv", md5::compute("a")).unwrap();
+        db.record_source_file("cba/2023-01.csv", &[1, 2]);
+        db.record_file_md5("amex/2023-02.csv", md5::compute("b")).unwrap();
+        db.record_source_file("amex/2023-02.csv", &[3]);
+        db.set_last_import(vec!["amex/2023-02.csv".into()]);
+
+        // Only transactions of the last import are deleted, manually inserted ones are kept
+        assert_eq!(db.rollback_last_import(), (vec!["amex/2023-02.csv".to_string()], vec![3]));
+        assert_eq!(query_ids(&mut db, None, None), vec![1, 2, 4]);
+        assert!(!db.file_exist("amex/2023-02.csv"));
+        assert!(db.file_with_md5(&md5::compute("b")).is_none());
+        assert!(db.file_exist("cba/2023-01.csv"));
+
+        // Nothing to roll back again
+        assert_eq!(db.rollback_last_import(), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_list_accounts() {
+        let db = test_db();
+        assert_eq!(db.list_accounts(), vec![
+            AccountSummary { account: "amex".into(), transactions: 2, net: -200.5 },
+            AccountSummary { account: "cba".into(), transactions: 2, net: 2950.0 },
+        ]);
+    }
+
+    #[test]
+    fn test_disable_autosave() {
+        let file_path = std::env::temp_dir().join(format!("perfidb_autosave_{}.db", std::process::id()));
+        let mut db = Database::new(file_path.to_str().unwrap().to_string());
+        db.disable_autosave();
+        db.save();
+        assert!(db.has_unsaved_changes());
+        assert!(!file_path.exists());
+
+        db.flush();
+        assert!(!db.has_unsaved_changes());
+        assert!(file_path.exists());
+        fs::remove_file(file_path).unwrap();
+    }
+
+    #[test]
+    fn test_readonly() {
+        let mut db = test_db();
+        db.set_readonly();
+        let labeller = Labeller::new(&crate::config::Config::empty());
+
+        db.apply_label_ops(2, LabelCommand::Manual(vec![LabelOp::new_add("salary")]), &labeller);
+        assert!(db.find_by_id(2).labels.is_empty());
+        assert!(db.delete(&[1]).is_empty());
+        assert!(db.merge_labels(&["food".to_string()], "dining").is_empty());
+        let trans_id = db.upsert(&Record { id: Some(1), ..Record::new("cba", "2023-01-05", "Changed", -1.0) });
+        assert_eq!(trans_id, None);
+        assert_eq!(db.find_by_id(1).description, "Woolworths Sydney");
+        assert_eq!(query_ids(&mut db, None, None), vec![1, 2, 3, 4]);
+
+        // Nothing to save in a read-only db, even with autosave disabled
+        db.disable_autosave();
+        db.save();
+        assert!(!db.has_unsaved_changes());
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut db = test_db();
+        let labeller = Labeller::new(&crate::config::Config::empty());
+        db.apply_label_ops(4, LabelCommand::Manual(vec![LabelOp::new_remove("food"), LabelOp::new_add("dining")]), &labeller);
+        db.delete_single(3);
+
+        let stats = db.compact();
+        // Empty bitmap of 'food', label 'food', date 2023-02-03 and token 'coles'. Token 'sydney' is still used by transaction 1
+        assert_eq!(stats.entries_removed, 4);
+        assert!(stats.bytes_reclaimed > 0);
+        assert!(db.label_minhash.lookup_by_string("food").is_none());
+        assert_eq!(db.labels(), vec!["dining", "grocery"]);
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Description(Operator::Match, "coles".into()))), Vec::<u32>::new());
+        assert_eq!(query_ids(&mut db, None, Some(Condition::Label(Operator::Eq, "grocery".into()))), vec![1]);
+
+        // Nothing left to compact
+        assert_eq!(db.compact().entries_removed, 0);
+    }
+
+    #[test]
+    fn test_amount_index_many_transactions() {
+        let mut db = Database::new("test.db".to_string());
+        for i in 0..50_000 {
+            db.upsert(&Record::new("cba", "2023-01-01", "test", (i % 2000) as f32 - 1000.0));
+        }
+
+        let all: RoaringBitmap = db.transactions.keys().cloned().collect();
+        let result = db.filter_transactions(&all, Condition::Amount(Operator::Gt, 990.0));
+
+        let expected = db.transactions.values().filter(|t| t.amount > 990.0).count() as u64;
+        assert_eq!(result.len(), expected);
+        assert_eq!(result.len(), 9 * 25);
+    }
 }
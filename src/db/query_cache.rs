@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+use roaring::RoaringBitmap;
+
+/// Number of query results kept in cache
+const QUERY_CACHE_CAPACITY: usize = 16;
+
+/// Least recently used cache of query results, so running the same query again, e.g. with a different
+/// `GROUP BY`, does not filter all transactions again. Keys are normalised queries, values are ids of the
+/// matching transactions. It must be cleared whenever transactions change.
+#[derive(Debug)]
+pub(crate) struct QueryCache {
+    capacity: usize,
+    /// Most recently used first
+    entries: VecDeque<(String, RoaringBitmap)>,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        QueryCache::new(QUERY_CACHE_CAPACITY)
+    }
+}
+
+impl QueryCache {
+    pub(crate) fn new(capacity: usize) -> QueryCache {
+        QueryCache { capacity, entries: VecDeque::with_capacity(capacity) }
+    }
+
+    /// Cached result of the query, which becomes the most recently used one
+    pub(crate) fn get(&mut self, key: &str) -> Option<RoaringBitmap> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index).unwrap();
+        let result = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(result)
+    }
+
+    /// Add a query result, evicting the least recently used one if the cache is full
+    pub(crate) fn put(&mut self, key: String, result: RoaringBitmap) {
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_back();
+        }
+        self.entries.push_front((key, result));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use roaring::RoaringBitmap;
+    use crate::db::query_cache::QueryCache;
+
+    #[test]
+    fn test_lru() {
+        let bitmap = |ids: &[u32]| ids.iter().copied().collect::<RoaringBitmap>();
+        let mut cache = QueryCache::new(2);
+        cache.put("a".into(), bitmap(&[1]));
+        cache.put("b".into(), bitmap(&[2]));
+
+        // 'a' is used, so 'b' is the least recently used and evicted
+        assert_eq!(cache.get("a"), Some(bitmap(&[1])));
+        cache.put("c".into(), bitmap(&[3]));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c"), Some(bitmap(&[3])));
+
+        // Replacing an existing key doesn't evict others
+        cache.put("c".into(), bitmap(&[4]));
+        assert_eq!(cache.get("a"), Some(bitmap(&[1])));
+        assert_eq!(cache.get("c"), Some(bitmap(&[4])));
+
+        cache.clear();
+        assert_eq!(cache.get("a"), None);
+    }
+}
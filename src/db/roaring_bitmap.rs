@@ -3,7 +3,6 @@ use std::fmt;
 use roaring::RoaringBitmap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use bytes::BufMut;
-use roaring::bitmap::Iter;
 use serde::de::Visitor;
 
 /// Create our own roaring bitmap type so we can implement
@@ -22,10 +21,6 @@ impl PerfidbRoaringBitmap {
     pub(crate) fn remove(&mut self, value: u32) -> bool {
         self.0.remove(value)
     }
-
-    pub(crate) fn iter(&self) -> Iter {
-        self.0.iter()
-    }
 }
 
 impl Serialize for PerfidbRoaringBitmap {
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 use std::ops::BitAnd;
 use roaring::RoaringBitmap;
 use serde::{Deserialize, Serialize};
@@ -40,24 +40,46 @@ impl SearchIndex {
         }
     }
 
-    pub(crate) fn search(&self, keyword: &str) -> HashSet<u32> {
+    /// Remove empty posting lists and the tokens no longer in any transaction.
+    /// Return number of posting lists removed.
+    pub(crate) fn compact(&mut self) -> usize {
+        let before = self.posting_list.len();
+        self.posting_list.retain(|_, bitmap| !bitmap.0.is_empty());
+        self.token_minhash.retain(|token_hash| self.posting_list.contains_key(token_hash));
+        before - self.posting_list.len()
+    }
+
+    /// Ids of tokens in the given text. Tokens not in any transaction are ignored.
+    pub(crate) fn token_ids(&self, text: &str) -> BTreeSet<u32> {
+        text.split_whitespace().filter_map(|token| self.token_minhash.lookup_by_string(token)).collect()
+    }
+
+    /// Transactions with any of the given tokens
+    pub(crate) fn search_any(&self, token_ids: &BTreeSet<u32>) -> RoaringBitmap {
+        let mut trans_ids = RoaringBitmap::new();
+        for token_id in token_ids {
+            if let Some(bitmap) = self.posting_list.get(token_id) {
+                trans_ids |= &bitmap.0;
+            }
+        }
+        trans_ids
+    }
+
+    pub(crate) fn search(&self, keyword: &str) -> RoaringBitmap {
         let mut maps: Vec<&RoaringBitmap> = vec![];
         for token in keyword.split_whitespace() {
-            if let Some(hash) = self.token_minhash.lookup_by_string(token) {
-                if let Some(bitmap) = self.posting_list.get(&hash) {
-                    maps.push(&bitmap.0);
-                }
+            match self.token_minhash.lookup_by_string(token).and_then(|hash| self.posting_list.get(&hash)) {
+                Some(bitmap) => maps.push(&bitmap.0),
+                // No transaction has this token, so no transaction can have all the tokens
+                None => return RoaringBitmap::new()
             }
         }
 
-        let mut trans_ids = HashSet::new();
+        let mut trans_ids = RoaringBitmap::new();
         if !maps.is_empty() {
-            let mut intersection = maps[0].clone();
+            trans_ids = maps[0].clone();
             for map in maps.into_iter().skip(1) {
-                intersection = intersection.bitand(map)
-            }
-            for trans_id in intersection.iter() {
-                trans_ids.insert(trans_id);
+                trans_ids = trans_ids.bitand(map)
             }
         }
         trans_ids
@@ -85,6 +107,24 @@ mod tests {
 
         let bytes = bincode::serialize(&search_index).unwrap();
         let search_index: SearchIndex = bincode::deserialize(&bytes).unwrap();
-        assert!(search_index.search("this").contains(&10));
+        assert!(search_index.search("this").contains(10));
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut search_index = SearchIndex::new();
+        let t = TransactionRecord {
+            id: 10,
+            account: "amex".to_string(),
+            date: Default::default(),
+            description: "Uber eats".to_string(),
+            amount: 10.0,
+            labels: LabelIdVec::empty(),
+        };
+        search_index.index(&t);
+        search_index.delete(10, "Uber eats");
+        assert_eq!(search_index.compact(), 2);
+        assert!(search_index.posting_list.is_empty());
+        assert!(search_index.search("uber").is_empty());
     }
 }
@@ -1,16 +1,15 @@
 use std::borrow::Cow::{self, Borrowed, Owned};
 
-use rustyline::completion::FilenameCompleter;
-use rustyline::highlight::{Highlighter, MatchingBracketHighlighter};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
 use rustyline::hint::HistoryHinter;
 use rustyline::validate::MatchingBracketValidator;
-use rustyline::{Completer, Helper, Hinter, Validator};
+use rustyline::{Context, Helper, Hinter, Validator};
+use crate::parser::KEYWORDS;
 
-#[derive(Helper, Completer, Hinter, Validator)]
+#[derive(Helper, Hinter, Validator)]
 pub(crate) struct PerfidbHelper {
-    #[rustyline(Completer)]
-    pub(crate) completer: FilenameCompleter,
-    pub(crate) highlighter: MatchingBracketHighlighter,
+    pub(crate) completer: SqlCompleter,
     #[rustyline(Validator)]
     pub(crate) validator: MatchingBracketValidator,
     #[rustyline(Hinter)]
@@ -18,6 +17,78 @@ pub(crate) struct PerfidbHelper {
     pub(crate) colored_prompt: String,
 }
 
+impl Completer for PerfidbHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        self.completer.complete(line, pos, ctx)
+    }
+}
+
+/// Complete SQL keywords, account names and labels based on what has been typed so far.
+/// File paths are completed after `EXPORT TO`.
+pub(crate) struct SqlCompleter {
+    filename_completer: FilenameCompleter,
+    /// Known account names, refreshed after each command
+    pub(crate) accounts: Vec<String>,
+    /// Known labels, refreshed after each command
+    pub(crate) labels: Vec<String>,
+}
+
+impl SqlCompleter {
+    pub(crate) fn new() -> SqlCompleter {
+        SqlCompleter {
+            filename_completer: FilenameCompleter::new(),
+            accounts: vec![],
+            labels: vec![],
+        }
+    }
+
+    /// Returns the start position of the word being completed and the candidates.
+    /// Returns `None` if a file path should be completed instead.
+    fn candidates(&self, line: &str, pos: usize) -> Option<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let start = before.rfind(|c: char| c.is_whitespace() || c == '\'' || c == '(' || c == ',').map(|i| i + 1).unwrap_or(0);
+        let word = &before[start..];
+        let previous_words: Vec<String> = before[..start].split_whitespace()
+            .map(|w| w.trim_matches('\'').to_ascii_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect();
+        let first_word = previous_words.first().map(String::as_str);
+        let previous_word = previous_words.last().map(String::as_str);
+        let in_quotes = before.matches('\'').count() % 2 == 1;
+
+        let choices: Vec<&str> = if first_word == Some("export") && previous_word == Some("to") {
+            return None;
+        } else if in_quotes || (first_word == Some("label") && previous_words.len() > 1) {
+            self.labels.iter().map(String::as_str).collect()
+        } else if previous_word == Some("from") || previous_word == Some("into") {
+            self.accounts.iter().map(String::as_str).collect()
+        } else {
+            KEYWORDS.to_vec()
+        };
+
+        // Keywords are suggested in lower case if user is typing in lower case
+        let lower_case = !word.is_empty() && word.chars().all(|c| !c.is_ascii_uppercase());
+        let candidates = choices.into_iter()
+            .filter(|c| c.to_ascii_lowercase().starts_with(&word.to_ascii_lowercase()))
+            .map(|c| if lower_case { c.to_ascii_lowercase() } else { c.to_string() })
+            .collect();
+        Some((start, candidates))
+    }
+}
+
+impl Completer for SqlCompleter {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        match self.candidates(line, pos) {
+            Some((start, candidates)) => Ok((start, candidates.into_iter().map(|c| Pair { display: c.clone(), replacement: c }).collect())),
+            None => self.filename_completer.complete(line, pos, ctx)
+        }
+    }
+}
+
 impl Highlighter for PerfidbHelper {
     fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
         &'s self,
@@ -35,11 +106,115 @@ impl Highlighter for PerfidbHelper {
         Owned("\x1b[2m".to_owned() + hint + "\x1b[m")
     }
 
-    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
-        self.highlighter.highlight(line, pos)
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Owned(highlight_sql(line))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _forced: bool) -> bool {
+        // Any typed character can change how the line is highlighted
+        true
+    }
+}
+
+const KEYWORD_COLOR: &str = "\x1b[1;34m";
+const STRING_COLOR: &str = "\x1b[32m";
+const NUMBER_COLOR: &str = "\x1b[33m";
+const RESET_COLOR: &str = "\x1b[0m";
+
+/// Colour keywords, string literals and numbers of a SQL statement
+fn highlight_sql(line: &str) -> String {
+    let mut highlighted = String::with_capacity(line.len() * 2);
+    let mut chars = line.char_indices().peekable();
+    let mut previous_char = ' ';
+    while let Some((start, c)) = chars.next() {
+        if c == '\'' {
+            // String literal, until the closing quote or end of line
+            let mut end = line.len();
+            for (i, c) in chars.by_ref() {
+                if c == '\'' {
+                    end = i + 1;
+                    break;
+                }
+            }
+            highlighted.push_str(STRING_COLOR);
+            highlighted.push_str(&line[start..end]);
+            highlighted.push_str(RESET_COLOR);
+            previous_char = '\'';
+        } else if c.is_ascii_digit() || (c == '-' && !previous_char.is_alphanumeric() && chars.peek().is_some_and(|(_, c)| c.is_ascii_digit())) {
+            // Numbers, including negative amounts and dates like 2023-01-05
+            let mut end = line.len();
+            while let Some((i, c)) = chars.peek() {
+                if c.is_ascii_digit() || *c == '.' || *c == '-' {
+                    chars.next();
+                } else {
+                    end = *i;
+                    break;
+                }
+            }
+            highlighted.push_str(NUMBER_COLOR);
+            highlighted.push_str(&line[start..end]);
+            highlighted.push_str(RESET_COLOR);
+            previous_char = '0';
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = line.len();
+            while let Some((i, c)) = chars.peek() {
+                if c.is_alphanumeric() || *c == '_' {
+                    chars.next();
+                } else {
+                    end = *i;
+                    break;
+                }
+            }
+            let word = &line[start..end];
+            if is_keyword(word) {
+                highlighted.push_str(KEYWORD_COLOR);
+                highlighted.push_str(word);
+                highlighted.push_str(RESET_COLOR);
+            } else {
+                highlighted.push_str(word);
+            }
+            previous_char = 'a';
+        } else {
+            highlighted.push(c);
+            previous_char = c;
+        }
+    }
+    highlighted
+}
+
+/// Check if a single word is part of a keyword, e.g. 'group' and 'by' are both from 'GROUP BY'
+fn is_keyword(word: &str) -> bool {
+    KEYWORDS.iter()
+        .flat_map(|k| k.split(|c: char| c.is_whitespace() || c == '(' || c == ')'))
+        .any(|k| !k.is_empty() && k.eq_ignore_ascii_case(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::{highlight_sql, SqlCompleter};
+
+    #[test]
+    fn test_highlight_sql() {
+        assert_eq!(
+            highlight_sql("select * from amex-plat where label = 'food' and amount < -20.5"),
+            "\x1b[1;34mselect\x1b[0m * \x1b[1;34mfrom\x1b[0m amex-plat \x1b[1;34mwhere\x1b[0m \x1b[1;34mlabel\x1b[0m = \x1b[32m'food'\x1b[0m \x1b[1;34mand\x1b[0m \x1b[1;34mamount\x1b[0m < \x1b[33m-20.5\x1b[0m"
+        );
+        assert_eq!(highlight_sql("selec 'unclosed"), "selec \x1b[32m'unclosed\x1b[0m");
+        assert_eq!(highlight_sql("WHERE date = 2023-01-05"), "\x1b[1;34mWHERE\x1b[0m \x1b[1;34mdate\x1b[0m = \x1b[33m2023-01-05\x1b[0m");
     }
 
-    fn highlight_char(&self, line: &str, pos: usize, forced: bool) -> bool {
-        self.highlighter.highlight_char(line, pos, forced)
+    #[test]
+    fn test_candidates() {
+        let mut completer = SqlCompleter::new();
+        completer.accounts = vec!["amex".to_string(), "cba".to_string()];
+        completer.labels = vec!["food".to_string(), "fuel".to_string(), "grocery".to_string()];
+
+        assert_eq!(completer.candidates("SEL", 3), Some((0, vec!["SELECT".to_string()])));
+        assert_eq!(completer.candidates("select * gro", 12), Some((9, vec!["group by".to_string()])));
+        assert_eq!(completer.candidates("select * from a", 15), Some((14, vec!["amex".to_string()])));
+        assert_eq!(completer.candidates("select * where label = 'f", 25), Some((24, vec!["food".to_string(), "fuel".to_string()])));
+        assert_eq!(completer.candidates("label 12 13 gr", 14), Some((12, vec!["grocery".to_string()])));
+        assert_eq!(completer.candidates("export to ./", 12), None);
+        assert_eq!(completer.candidates("export to './", 13), None);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::Path;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use crate::csv_reader::Record;
+
+/// A transaction in json file, same shape as transactions exported by perfidb
+#[derive(Deserialize)]
+struct JsonTransaction {
+    #[serde(rename = "_perfidb_transaction_id", default)]
+    id: Option<u32>,
+    #[serde(rename = "_perfidb_account", default)]
+    account: Option<String>,
+    date: NaiveDateTime,
+    description: String,
+    amount: f32,
+    /// Labels joined by a bar |
+    #[serde(rename = "_perfidb_label", default)]
+    labels: Option<String>,
+}
+
+/// Read transactions from a json file containing an array of transaction objects
+pub(crate) fn read_transactions(table_name: &str, file_path: &Path) -> anyhow::Result<Vec<Record>> {
+    parse_transactions(table_name, &fs::read_to_string(file_path)?)
+}
+
+fn parse_transactions(table_name: &str, json: &str) -> anyhow::Result<Vec<Record>> {
+    let transactions: Vec<JsonTransaction> = serde_json::from_str(json)?;
+    Ok(transactions.into_iter().map(|t| Record {
+        id: t.id,
+        account: t.account.unwrap_or_else(|| table_name.to_string()),
+        date: t.date,
+        description: t.description,
+        amount: t.amount,
+        labels: t.labels.filter(|labels| !labels.is_empty()).map(|labels| labels.split('|').map(str::to_string).collect()),
+        currency: None,
+        balance: None,
+        raw_amount: None,
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_reader::parse_transactions;
+
+    #[test]
+    fn test_parse_transactions() {
+        let json = r#"[
+            {"_perfidb_transaction_id": 3, "_perfidb_account": "amex", "date": "2023-02-21T00:00:00", "description": "Coles", "amount": -80.5, "_perfidb_label": "grocery|food"},
+            {"date": "2023-02-22T09:30:00", "description": "Salary", "amount": 3000, "_perfidb_label": ""}
+        ]"#;
+        let records = parse_transactions("cba", json).unwrap();
+        assert_eq!(records[0].id, Some(3));
+        assert_eq!(records[0].account, "amex");
+        assert_eq!(records[0].labels, Some(vec!["grocery".to_string(), "food".to_string()]));
+        assert_eq!(records[1].id, None);
+        assert_eq!(records[1].account, "cba");
+        assert_eq!(records[1].labels, None);
+        assert!(parse_transactions("cba", "{}").is_err());
+    }
+}
@@ -1,81 +1,156 @@
+use std::collections::BTreeSet;
 use std::io::{Error, stdout};
 
 use crossterm::{execute, terminal};
-use crossterm::cursor::{MoveTo, MoveToColumn};
-use crossterm::event::{Event, KeyCode, read};
-use crossterm::style::{self, Color, SetBackgroundColor, SetForegroundColor};
+use crossterm::cursor::{MoveLeft, MoveTo, MoveToColumn};
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, read};
+use crossterm::style::{self, Color, ResetColor, SetBackgroundColor, SetForegroundColor};
 use crossterm::terminal::{ClearType, EnterAlternateScreen, LeaveAlternateScreen};
 
 use crate::{Database, db};
+use crate::audit::AuditLog;
+use crate::config::Config;
 use crate::transaction::Transaction;
+use crate::util::{colored_labels, fit_terminal_width, truncate_with_ellipsis};
+
+/// Width of the id, account and date columns before description in a row, including borders
+const LEADING_COLUMNS_WIDTH: usize = 39;
+
+/// Width of the amount column between description and labels in a row, including borders
+const AMOUNT_COLUMN_WIDTH: usize = 16;
+
+/// Width of the columns other than description in a row, including borders. Columns are padded to fixed widths,
+/// unlike `SELECT *` results, and labels are given room for 24 characters so rows don't wrap in the terminal.
+const OTHER_COLUMNS_WIDTH: usize = LEADING_COLUMNS_WIDTH + AMOUNT_COLUMN_WIDTH + 24 + 2;
+
+/// Min width of the labels column, shorter labels are padded with spaces
+const LABELS_WIDTH: usize = 15;
 
 /// Open a terminal dialog to label transactions in a live table
 /// It takes last_query_results as a list of ids because we might change labels, so we'll need to re-render labels.
-pub(crate) fn live_label(last_query_results: Vec<u32>, db: &mut Database, auto_label_rules_file: &str) -> Result<(), Error> {
-    let mut transactions: Vec<Transaction> = last_query_results.iter().map(|trans_id| db.find_by_id(*trans_id)).collect();
+pub(crate) fn live_label(last_query_results: Vec<u32>, db: &mut Database, auto_label_rules_file: &str, audit_log: &AuditLog) -> Result<(), Error> {
+    let mut transactions: Vec<Transaction> = load_transactions(&last_query_results, db);
+    let labeller = db.labeller(auto_label_rules_file);
+    let config = db.config(auto_label_rules_file);
 
-    execute!(stdout(), EnterAlternateScreen, MoveTo(0, 0))?;
+    execute!(stdout(), EnterAlternateScreen, EnableBracketedPaste, MoveTo(0, 0))?;
     terminal::enable_raw_mode()?;
-    // TODO: handle terminal resize
     let (_columns, rows) = terminal::size()?;
 
-    let mut window = Window {
-        rows,
-        transactions_count: transactions.len(),
-        offset: 0,
-        selected_row: 0
-    };
+    let mut window = Window::new(rows, transactions.len());
 
-    repaint_window(window.repaint(), &transactions, window.selected_row);
+    repaint_window(window.repaint(), &transactions, &window, &config);
 
     loop {
         // `read()` blocks until an `Event` is available
         match read().unwrap() {
             Event::FocusGained => println!("FocusGained"),
             Event::FocusLost => println!("FocusLost"),
-            Event::Key(event) => {
-                if let KeyCode::Char(c) = event.code {
+            Event::Key(event) => match event.code {
+                // Escape clears the search filter and restores the full list
+                KeyCode::Esc => {
+                    transactions = load_transactions(&last_query_results, db);
+                    window = Window::new(window.rows, transactions.len());
+                    execute!(stdout(), terminal::Clear(ClearType::All)).unwrap();
+                    repaint_window(window.visible_rows(), &transactions, &window, &config);
+                },
+                KeyCode::Home | KeyCode::End if !transactions.is_empty() => {
+                    jump(event.code == KeyCode::Home, &transactions, &mut window, &config);
+                },
+                KeyCode::Char(c) => {
                     match c {
                         'q' => break,
+                        '/' => {
+                            execute!(stdout(), MoveTo(0, window.rows - 1), terminal::Clear(ClearType::CurrentLine), style::Print("/")).unwrap();
+                            if let Some(search_text) = read_input()? {
+                                let search_text = search_text.trim().to_lowercase();
+
+                                // Search from the full list so a new search replaces the previous filter
+                                transactions = load_transactions(&last_query_results, db).into_iter()
+                                    .filter(|t| t.description.to_lowercase().contains(&search_text))
+                                    .collect();
+                                window = Window::new(window.rows, transactions.len());
+                            }
+
+                            execute!(stdout(), terminal::Clear(ClearType::All)).unwrap();
+                            repaint_window(window.visible_rows(), &transactions, &window, &config);
+                        },
+                        // Nothing to move, mark, label or delete once all transactions are deleted
+                        _ if transactions.is_empty() => {},
+                        'l' | 'd' if db.is_readonly() => {},
                         'j' => {
                             let delta = window.move_down();
-                            repaint_window(delta, &transactions, window.selected_row);
+                            repaint_window(delta, &transactions, &window, &config);
                         },
                         'k' => {
                             let delta = window.move_up();
-                            repaint_window(delta, &transactions, window.selected_row);
+                            repaint_window(delta, &transactions, &window, &config);
+                        },
+                        'g' | 'G' => jump(c == 'g', &transactions, &mut window, &config),
+                        ' ' => {
+                            let delta = window.toggle_mark();
+                            repaint_window(delta, &transactions, &window, &config);
                         },
                         'l' => {
-                            execute!(stdout(), MoveTo(114, window.selected_row)).unwrap();
-                            terminal::disable_raw_mode().unwrap();
-                            let mut new_labels = String::new();
-                            std::io::stdin().read_line(&mut new_labels)?;
-                            let trans_id = transactions[window.selected_transaction_index()].id;
-
-                            let result = db::label_op::parse_label_command(&new_labels);
-                            if let Ok((_, label_cmd)) = result {
-                                db.apply_label_ops(trans_id, label_cmd, auto_label_rules_file);
+                            execute!(stdout(), MoveTo(labels_column(&config), window.selected_row)).unwrap();
+                            if let Some(new_labels) = read_input()? {
+                                // Apply label ops to all marked transactions, or to the highlighted one if nothing is marked
+                                let trans_indices: Vec<usize> = if window.marked.is_empty() {
+                                    vec![window.selected_transaction_index()]
+                                } else {
+                                    window.marked.iter().cloned().collect()
+                                };
+
+                                let result = db::label_op::parse_label_command(&new_labels);
+                                if let Ok((_, label_cmd)) = result {
+                                    for trans_index in &trans_indices {
+                                        let trans_id = transactions[*trans_index].id;
+                                        db.apply_label_ops(trans_id, label_cmd.clone(), &labeller);
+                                        transactions[*trans_index].labels = db.find_by_id(trans_id).labels;
+                                    }
+                                    db.save();
+                                    let trans_ids: Vec<u32> = trans_indices.iter().map(|i| transactions[*i].id).collect();
+                                    audit_log.record(&format!("LIVE LABEL {}", new_labels.trim()), &trans_ids);
+                                }
                             }
 
-                            transactions[window.selected_transaction_index()].labels = db.find_by_id(trans_id).labels;
-                            terminal::enable_raw_mode().unwrap();
-                            repaint_window(vec![(window.selected_row, window.offset + window.selected_row as usize, true)], &transactions, window.selected_row);
-                            execute!(stdout(), MoveTo(114, window.selected_row)).unwrap();
+                            repaint_window(window.visible_rows(), &transactions, &window, &config);
+                            execute!(stdout(), MoveTo(labels_column(&config), window.selected_row)).unwrap();
+                        },
+                        'd' => {
+                            let trans_index = window.selected_transaction_index();
+                            let trans_id = transactions[trans_index].id;
+                            execute!(stdout(), MoveTo(0, window.rows - 1), terminal::Clear(ClearType::CurrentLine)).unwrap();
+                            if confirm(&format!("Delete transaction {trans_id}? This can not be undone. (y/n) "))? {
+                                let deleted = db.delete(&[trans_id]);
+                                if !deleted.is_empty() {
+                                    audit_log.record("LIVE DELETE", &deleted);
+                                    transactions.remove(trans_index);
+                                    window.remove(trans_index);
+                                }
+                            }
+
+                            execute!(stdout(), terminal::Clear(ClearType::All)).unwrap();
+                            repaint_window(window.visible_rows(), &transactions, &window, &config);
                         },
                         _ => {}
                     }
-                }
+                },
+                _ => {}
             },
             Event::Mouse(event) => println!("{:?}", event),
-            #[cfg(feature = "bracketed-paste")]
-            Event::Paste(data) => println!("{:?}", data),
-            Event::Resize(width, height) => println!("New size {}x{}", width, height),
-            Event::Paste(s) => println!("{}", s),
+            Event::Resize(_width, height) => {
+                window.resize(height);
+                execute!(stdout(), terminal::Clear(ClearType::All)).unwrap();
+                repaint_window(window.visible_rows(), &transactions, &window, &config);
+            },
+            // Pasted text is only accepted while typing labels or search text, see `read_input()`
+            Event::Paste(_) => {},
         }
     }
 
     terminal::disable_raw_mode()?;
-    execute!(stdout(), LeaveAlternateScreen)?;
+    execute!(stdout(), DisableBracketedPaste, LeaveAlternateScreen)?;
 
     Ok(())
 }
@@ -92,9 +167,22 @@ struct Window {
 
     /// The row that is selected. 0 <= selected_row < rows
     selected_row: u16,
+
+    /// Indices of transactions marked for bulk labelling
+    marked: BTreeSet<usize>,
 }
 
 impl Window {
+    fn new(rows: u16, transactions_count: usize) -> Window {
+        Window {
+            rows,
+            transactions_count,
+            offset: 0,
+            selected_row: 0,
+            marked: BTreeSet::new(),
+        }
+    }
+
     fn selected_transaction_index(&self) -> usize {
         self.offset + self.selected_row as usize
     }
@@ -109,6 +197,79 @@ impl Window {
         delta
     }
 
+    /// All rows currently visible in this window, used to repaint the whole screen
+    fn visible_rows(&self) -> Vec<(u16, usize, bool)> {
+        let remaining_trans_count = self.transactions_count - self.offset;
+        let print_trans_count :usize = if remaining_trans_count > self.rows as usize { self.rows as usize } else { remaining_trans_count };
+        let mut delta :Vec<(u16, usize, bool)> = vec![];
+        for i in 0..print_trans_count {
+            delta.push((i as u16, self.offset + i, i == self.selected_row as usize));
+        }
+        delta
+    }
+
+    /// Update the number of rows after terminal is resized. The selected transaction is kept on screen
+    /// by clamping the selected row to the new height and scrolling the offset if needed.
+    fn resize(&mut self, rows: u16) {
+        let rows = rows.max(1);
+        let trans_index = self.selected_transaction_index();
+        if self.selected_row >= rows {
+            self.selected_row = rows - 1;
+            self.offset = trans_index - self.selected_row as usize;
+        }
+
+        // If the window grows, scroll back so there is no empty space below the last transaction
+        let max_offset = self.transactions_count.saturating_sub(rows as usize);
+        if self.offset > max_offset {
+            self.selected_row += (self.offset - max_offset) as u16;
+            self.offset = max_offset;
+        }
+        self.rows = rows;
+    }
+
+    /// Update the window after the transaction at given index is removed
+    fn remove(&mut self, trans_index: usize) {
+        self.transactions_count -= 1;
+
+        // Indices after the removed transaction shift up by one
+        self.marked = self.marked.iter()
+            .filter(|i| **i != trans_index)
+            .map(|i| if *i > trans_index { i - 1 } else { *i })
+            .collect();
+
+        // Scroll back if there are empty rows at the bottom, keeping the selection at the same transaction index
+        if self.offset > 0 && self.offset + self.rows as usize > self.transactions_count {
+            self.offset -= 1;
+            self.selected_row += 1;
+        }
+
+        // The last transaction was removed, move selection up
+        if self.selected_transaction_index() >= self.transactions_count && self.selected_row > 0 {
+            self.selected_row -= 1;
+        }
+    }
+
+    /// Select the first transaction
+    fn move_to_top(&mut self) {
+        self.offset = 0;
+        self.selected_row = 0;
+    }
+
+    /// Select the last transaction, scrolling so the window is filled up to it
+    fn move_to_bottom(&mut self) {
+        self.offset = self.transactions_count.saturating_sub(self.rows as usize);
+        self.selected_row = (self.transactions_count.saturating_sub(1) - self.offset) as u16;
+    }
+
+    /// Mark or unmark the selected transaction
+    fn toggle_mark(&mut self) -> Vec<(u16, usize, bool)> {
+        let trans_index = self.selected_transaction_index();
+        if !self.marked.remove(&trans_index) {
+            self.marked.insert(trans_index);
+        }
+        vec![(self.selected_row, trans_index, true)]
+    }
+
     fn move_down(&mut self) -> Vec<(u16, usize, bool)> {
         if self.offset + self.selected_row as usize >= self.transactions_count - 1 {
             return vec![];
@@ -171,28 +332,159 @@ impl Window {
     }
 }
 
-fn repaint_window(delta: Vec<(u16, usize, bool)>, transactions: &[Transaction], selected_row: u16) {
+/// Jump the selection to the first or last transaction and repaint the whole screen
+fn jump(to_top: bool, transactions: &[Transaction], window: &mut Window, config: &Config) {
+    if to_top {
+        window.move_to_top();
+    } else {
+        window.move_to_bottom();
+    }
+    execute!(stdout(), terminal::Clear(ClearType::All)).unwrap();
+    repaint_window(window.visible_rows(), transactions, window, config);
+}
+
+/// Read a line of text typed at current cursor position, e.g. new labels or search text.
+/// Pasted text is appended to the input. Returns `None` if user presses `Esc` to cancel.
+fn read_input() -> Result<Option<String>, Error> {
+    let mut input = String::new();
+    loop {
+        match read()? {
+            Event::Key(event) => match event.code {
+                KeyCode::Enter => return Ok(Some(input)),
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Backspace if input.pop().is_some() => {
+                    execute!(stdout(), MoveLeft(1), style::Print(' '), MoveLeft(1))?;
+                },
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    execute!(stdout(), style::Print(c))?;
+                },
+                _ => {}
+            },
+            Event::Paste(text) => {
+                // Line breaks would otherwise end up in the labels
+                let text = text.replace(['\r', '\n'], " ");
+                input.push_str(&text);
+                execute!(stdout(), style::Print(text))?;
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Print the question at current cursor position and wait for a key. Returns true only if user presses `y`.
+fn confirm(question: &str) -> Result<bool, Error> {
+    execute!(stdout(), style::Print(question))?;
+    loop {
+        if let Event::Key(event) = read()? {
+            return Ok(event.code == KeyCode::Char('y') || event.code == KeyCode::Char('Y'));
+        }
+    }
+}
+
+/// Load transactions by ids, skipping any that no longer exist in database
+fn load_transactions(trans_ids: &[u32], db: &Database) -> Vec<Transaction> {
+    trans_ids.iter().filter_map(|trans_id| db.search_by_id(*trans_id)).collect()
+}
+
+/// Repaint rows of the window. Descriptions are cut to the configured width, or shorter to fit the terminal.
+fn repaint_window(delta: Vec<(u16, usize, bool)>, transactions: &[Transaction], window: &Window, config: &Config) {
+    let description_width = description_width(config);
     for (row, trans_index, highlight) in delta {
         execute!(stdout(), MoveTo(0, row), terminal::Clear(ClearType::CurrentLine)).unwrap();
-        print_transaction(&transactions[trans_index], highlight);
+        print_transaction(&transactions[trans_index], highlight, window.marked.contains(&trans_index), description_width, config);
     }
-    execute!(stdout(), MoveTo(0, selected_row)).unwrap();
+    execute!(stdout(), MoveTo(0, window.selected_row)).unwrap();
 }
 
 /// Print a single transaction, in current terminal line
-fn print_transaction(t: &Transaction, highlight: bool) {
-    if highlight {
-        execute!(stdout(), SetForegroundColor(Color::Black), SetBackgroundColor(Color::White)).unwrap();
-    }
-    let desc = if t.description.len() > 50 {
-        let mut cut_down_version = t.description[0..49].to_owned();
-        cut_down_version.push('…');
-        cut_down_version
-    } else {
-        t.description.clone()
-    };
-    execute!(stdout(), style::Print(format!("| {:4} | {:14} | {} | {:50} | {:10} | {:15} |", t.id, t.account, t.date, desc, t.amount, t.tags_display())), MoveToColumn(0)).unwrap();
-    if highlight {
-        execute!(stdout(), SetForegroundColor(Color::White), SetBackgroundColor(Color::Black)).unwrap();
+/// Max number of characters of descriptions in a row, from config and terminal width
+fn description_width(config: &Config) -> usize {
+    fit_terminal_width(config.description_width(), OTHER_COLUMNS_WIDTH)
+}
+
+/// Column where labels start in a row, where the cursor is placed to edit labels
+fn labels_column(config: &Config) -> u16 {
+    (LEADING_COLUMNS_WIDTH + description_width(config) + AMOUNT_COLUMN_WIDTH) as u16
+}
+
+fn print_transaction(t: &Transaction, highlight: bool, marked: bool, description_width: usize, config: &Config) {
+    // Marked rows are shown in yellow, the highlighted row is shown in reverse colour
+    match (highlight, marked) {
+        (true, true) => execute!(stdout(), SetForegroundColor(Color::Black), SetBackgroundColor(Color::Yellow)).unwrap(),
+        (true, false) => execute!(stdout(), SetForegroundColor(Color::Black), SetBackgroundColor(Color::White)).unwrap(),
+        (false, true) => execute!(stdout(), SetForegroundColor(Color::Yellow)).unwrap(),
+        (false, false) => {}
+    }
+    let desc = truncate_with_ellipsis(&t.description, description_width);
+    // Labels are coloured only in plain rows, colours would reset the background of highlighted or marked rows.
+    // Padding is added separately as colour codes don't take up any width.
+    let labels = t.tags_display();
+    let padding = " ".repeat(LABELS_WIDTH.saturating_sub(labels.chars().count()));
+    let labels = if highlight || marked { labels } else { colored_labels(&t.labels, config) };
+    execute!(stdout(), style::Print(format!("| {:4} | {:14} | {} | {:description_width$} | {:10} | {labels}{padding} |", t.id, t.account, t.date, desc, t.amount)), MoveToColumn(0)).unwrap();
+    if highlight || marked {
+        execute!(stdout(), ResetColor).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use crate::live_edit::Window;
+
+    #[test]
+    fn test_resize() {
+        let mut window = Window { rows: 10, transactions_count: 30, offset: 5, selected_row: 8, marked: BTreeSet::new() };
+
+        // Shrinking keeps the selected transaction on screen
+        window.resize(4);
+        assert_eq!(window.selected_row, 3);
+        assert_eq!(window.selected_transaction_index(), 13);
+
+        // Growing beyond the remaining transactions scrolls back
+        window.resize(25);
+        assert_eq!(window.offset, 5);
+        assert_eq!(window.selected_transaction_index(), 13);
+        window.resize(30);
+        assert_eq!(window.offset, 0);
+        assert_eq!(window.selected_row, 13);
+    }
+
+    #[test]
+    fn test_move_to_top_and_bottom() {
+        let mut window = Window { rows: 10, transactions_count: 30, offset: 5, selected_row: 3, marked: BTreeSet::new() };
+        window.move_to_bottom();
+        assert_eq!(window.offset, 20);
+        assert_eq!(window.selected_row, 9);
+        assert_eq!(window.selected_transaction_index(), 29);
+
+        window.move_to_top();
+        assert_eq!(window.selected_transaction_index(), 0);
+
+        // Fewer transactions than rows
+        let mut window = Window { rows: 10, transactions_count: 3, offset: 0, selected_row: 0, marked: BTreeSet::new() };
+        window.move_to_bottom();
+        assert_eq!(window.offset, 0);
+        assert_eq!(window.selected_row, 2);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut window = Window { rows: 5, transactions_count: 3, offset: 0, selected_row: 2, marked: BTreeSet::from([0, 1]) };
+
+        // Removing the last transaction moves selection up
+        window.remove(2);
+        assert_eq!(window.transactions_count, 2);
+        assert_eq!(window.selected_row, 1);
+
+        // Marked indices after the removed one shift up
+        window.remove(0);
+        assert_eq!(window.marked, BTreeSet::from([0]));
+        assert_eq!(window.selected_row, 0);
+
+        window.remove(0);
+        assert_eq!(window.transactions_count, 0);
+        assert!(window.marked.is_empty());
     }
 }
@@ -1,27 +1,28 @@
 use std::{fs, process};
 
 use std::path::{Path, PathBuf};
+use anyhow::Context;
 use clap::Parser;
 use env_logger::Env;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use rustyline::error::ReadlineError;
 use toml::Value;
 use std::io::Write;
 use rustyline::{Cmd, CompletionType, EditMode, KeyEvent};
-use rustyline::completion::FilenameCompleter;
-use rustyline::highlight::MatchingBracketHighlighter;
 use rustyline::hint::HistoryHinter;
 use rustyline::validate::MatchingBracketValidator;
 
 extern crate dirs;
 extern crate core;
 
+use crate::audit::AuditLog;
 use crate::config::Config;
 use crate::db::Database;
 
-mod common;
+mod audit;
 mod db;
 mod csv_reader;
+mod json_reader;
 mod transaction;
 mod config;
 mod labeller;
@@ -30,6 +31,7 @@ mod editor;
 mod util;
 mod controller;
 mod parser;
+#[cfg(test)]
 mod tokeniser;
 
 #[derive(Parser)]
@@ -39,19 +41,33 @@ struct Cli {
     #[arg(short, long, value_name = "DATABASE_FILE")]
     file: Option<String>,
 
-    /// The dir that contains bank transaction csv files
+    /// The dir that contains bank transaction csv files. If not specified, 'import_root_dir' in the auto labelling rules
+    /// file is used. Only needed for IMPORT.
     #[arg(short, long, value_name = "IMPORT_ROOT_DIR")]
-    import_root_dir: String,
+    import_root_dir: Option<String>,
 
     /// A toml file containing auto labelling regex. By default perfidb will try look for '~/.peridb/auto_label_rules.toml' file.
     /// An example toml file is generated in '~/.perfidb' directory. Remove '.example' suffix to start using this file.
     #[arg(short, long = "auto-label-rules", value_name = "TOML_PATH")]
     auto_label_rules_file: Option<String>,
+
+    /// Do not save database after each change. Use 'SAVE' statement to write changes to disk.
+    #[arg(long)]
+    no_autosave: bool,
+
+    /// Open database in read-only mode. Statements modifying the database, e.g. LABEL, DELETE, INSERT, are ignored.
+    #[arg(long)]
+    readonly: bool,
 }
 
 static COMMAND_HISTORY_FILE: &str = ".perfidb_history";
 
-static WELCOME_MESSAGE: &'static str = r#"
+static AUDIT_LOG_FILE: &str = "audit.log";
+
+/// Statements run on startup, e.g. to create views
+static INIT_SCRIPT_FILE: &str = "init.perfidb";
+
+static WELCOME_MESSAGE: &str = r#"
 +-----------------------------------------------------+
 |                                                     |
 | Welcome to PerfiDB                                  |
@@ -71,8 +87,20 @@ fn main() {
 
     info!("{}", WELCOME_MESSAGE);
 
-    let import_root_dir = PathBuf::from(cli.import_root_dir);
-    let mut db = init_and_load_database(&cli.file, &import_root_dir);
+    let mut db = match init_and_load_database(&cli.file) {
+        Ok(db) => db,
+        Err(e) => {
+            error!("{e:#}");
+            process::exit(1);
+        }
+    };
+    if cli.readonly {
+        info!("Database is opened in read-only mode.");
+        db.set_readonly();
+    }
+    if cli.no_autosave {
+        db.disable_autosave();
+    }
     let auto_label_rules_file = match &cli.auto_label_rules_file {
         Some(f) => f.clone(),
         None => {
@@ -80,6 +108,9 @@ fn main() {
             user_home_dir.join(".perfidb").join("auto_label_rules.toml").as_path().display().to_string()
         }
     };
+    let import_root_dir = cli.import_root_dir
+        .or_else(|| Config::load_from_file(&auto_label_rules_file).import_root_dir)
+        .map(PathBuf::from);
 
 
     let config = rustyline::Config::builder()
@@ -88,8 +119,7 @@ fn main() {
         .edit_mode(EditMode::Emacs)
         .build();
     let helper = editor::PerfidbHelper {
-        completer: FilenameCompleter::new(),
-        highlighter: MatchingBracketHighlighter::new(),
+        completer: editor::SqlCompleter::new(),
         hinter: HistoryHinter {},
         colored_prompt: "# ".to_owned(),
         validator: MatchingBracketValidator::new(),
@@ -100,14 +130,24 @@ fn main() {
     rl.bind_sequence(KeyEvent::alt('p'), Cmd::HistorySearchBackward);
 
     let command_history_file: PathBuf = perfidb_home_path().join(COMMAND_HISTORY_FILE);
+    let audit_log = AuditLog::new(perfidb_home_path().join(AUDIT_LOG_FILE));
 
     if rl.load_history(command_history_file.as_path()).is_err() {
         debug!("No previous command history found.");
     }
 
+    run_init_script(&perfidb_home_path().join(INIT_SCRIPT_FILE), &mut db, import_root_dir.as_ref(), &auto_label_rules_file, &audit_log);
+
 
     let mut sql_buffer :Vec<String> = vec![];
     loop {
+        // Refresh account names and labels for tab completion, they may change after each command
+        if let Some(helper) = rl.helper_mut() {
+            helper.completer.accounts = db.accounts();
+            helper.completer.labels = db.labels();
+        }
+
+
         let readline = rl.readline("# ");
         match readline {
             Ok(line) => {
@@ -122,7 +162,7 @@ fn main() {
                         },
                         "live" => {
                             if let Some(last_results) = &db.last_query_results {
-                                live_edit::live_label(last_results.clone(), &mut db, &auto_label_rules_file).unwrap();
+                                live_edit::live_label(last_results.clone(), &mut db, &auto_label_rules_file, &audit_log).unwrap();
                             } else {
                                 info!("No recent select results");
                             }
@@ -132,18 +172,28 @@ fn main() {
                     }
                 }
 
-                let is_last = line.ends_with(';');
                 if !line.is_empty() {
                     sql_buffer.push(line.to_string());
                 }
-                if is_last {
-                    let sql = sql_buffer.join("\n");
-                    let _ = rl.add_history_entry(sql.trim());
 
-                    // Remove leading and trailing space and semicolon
-                    let pattern :&[_] = &[' ', ';'];
+                // Comments are removed from the whole buffer, as a block comment can span multiple lines
+                let sql_with_comments = sql_buffer.join("\n");
+                let sql = match parser::strip_comments(&sql_with_comments) {
+                    Some(sql) => sql,
+                    // Still inside a block comment
+                    None => continue
+                };
+
+                if sql.trim().is_empty() {
+                    // Nothing but comments
+                    sql_buffer.clear();
+                } else if sql.trim_end().ends_with(';') {
+                    let _ = rl.add_history_entry(sql_with_comments.trim());
+
+                    // Remove leading and trailing whitespace and semicolon
+                    let pattern :&[_] = &[' ', '\n', ';'];
                     let sql = sql.trim_matches(pattern).to_string();
-                    let result = controller::parse_and_run_command(&mut db, &import_root_dir, sql, &auto_label_rules_file);
+                    let result = controller::parse_and_run_command(&mut db, import_root_dir.as_ref(), sql, &auto_label_rules_file, &audit_log);
 
                     if let Err(err) = result {
                         error!("{}", err);
@@ -166,6 +216,18 @@ fn main() {
             }
         }
     }
+    if db.has_unsaved_changes() {
+        let answer = rl.readline("There are unsaved changes. Save before exit? yes or no, default is 'yes': ");
+        // Save if unable to read an answer, e.g. CTRL-D, so changes are not lost by accident
+        let save = answer.map(|answer| {
+            let answer = answer.trim().to_lowercase();
+            answer.is_empty() || answer == "yes" || answer == "y"
+        }).unwrap_or(true);
+        if save {
+            db.flush();
+            info!("Database saved.");
+        }
+    }
     rl.save_history(command_history_file.as_path()).unwrap();
 }
 
@@ -174,10 +236,35 @@ fn perfidb_home_path() -> PathBuf {
     user_home.join(".perfidb")
 }
 
-fn init_and_load_database(file_from_cli: &Option<String>, _import_root_dir: &PathBuf) -> Database {
+/// Run statements in the init script, if it exists. Errors are logged and do not stop the remaining statements.
+fn run_init_script(script_path: &Path, db: &mut Database, import_root_dir: Option<&PathBuf>, auto_label_rules_file: &str, audit_log: &AuditLog) {
+    if !script_path.is_file() {
+        return;
+    }
+    let script = match fs::read_to_string(script_path) {
+        Ok(script) => script,
+        Err(e) => {
+            warn!("Unable to read init script {}: {e}", script_path.display());
+            return;
+        }
+    };
+    let Some(script) = parser::strip_comments(&script) else {
+        warn!("Comment not closed in init script {}, the script is ignored.", script_path.display());
+        return;
+    };
+
+    info!("Running init script {}", script_path.display());
+    for sql in parser::split_statements(&script) {
+        if let Err(err) = controller::parse_and_run_command(db, import_root_dir, sql.clone(), auto_label_rules_file, audit_log) {
+            warn!("Error in init script, statement '{sql}' is skipped: {err}");
+        }
+    }
+}
+
+fn init_and_load_database(file_from_cli: &Option<String>) -> anyhow::Result<Database> {
     if let Some(file_from_cli) = file_from_cli {
         info!("Loading database from: {}", file_from_cli);
-        Database::load(file_from_cli).unwrap()
+        load_database(file_from_cli)
     } else {
         let perfidb_home_dir = perfidb_home_path();
         if perfidb_home_dir.exists() && perfidb_home_dir.is_file() {
@@ -194,11 +281,48 @@ fn init_and_load_database(file_from_cli: &Option<String>, _import_root_dir: &Pat
         if !db_file.exists() {
             let db_file_path = db_file.as_path().display().to_string();
             info!("Creating database file in {}", db_file_path);
-            let db = Database::new(db_file_path);
+            let mut db = Database::new(db_file_path);
             db.save();
         }
 
-        Database::load(db_file.as_path().to_str().unwrap()).unwrap()
+        load_database(db_file.as_path().to_str().unwrap())
+    }
+}
+
+/// Load database file. If the file is corrupt, ask user whether to restore from backup or start with an empty database.
+fn load_database(path: &str) -> anyhow::Result<Database> {
+    let error = match Database::load(path) {
+        Ok(db) => return Ok(db),
+        Err(e) => e
+    };
+    error!("Unable to load database file {path}: {error:#}");
+
+    let backup = db::backup_path(path);
+    let has_backup = Path::new(&backup).is_file();
+    if has_backup {
+        println!("Restore from backup {backup} (r), start with an empty database (e), or quit (q)? default is 'q': ");
+    } else {
+        println!("No backup found. Start with an empty database (e), or quit (q)? default is 'q': ");
+    }
+    let mut user_input = String::new();
+    std::io::stdin().read_line(&mut user_input)?;
+    let user_input = user_input.trim().to_lowercase();
+    if !(user_input == "e" || (has_backup && user_input == "r")) {
+        return Err(error);
+    }
+
+    // Keep the corrupt file, it may still be recovered manually
+    let corrupt = format!("{path}.corrupt");
+    fs::rename(path, &corrupt).with_context(|| format!("Unable to move corrupt database file to {corrupt}"))?;
+    info!("Corrupt database file is moved to {corrupt}");
+
+    if user_input == "r" {
+        fs::copy(&backup, path).with_context(|| format!("Unable to restore from {backup}"))?;
+        Database::load(path).with_context(|| format!("Backup {backup} is also unreadable"))
+    } else {
+        let mut db = Database::new(path.to_string());
+        db.save();
+        Ok(db)
     }
 }
 
@@ -206,6 +330,7 @@ fn create_auto_label_rules_example(perfidb_home_dir: &Path) {
     let mut config = Config::empty();
     config.labels.insert("grocery".to_string(), Value::Array(vec![Value::String("woolworths".to_string()), Value::String("coles".to_string())]));
     config.labels.insert("transfer".to_string(), Value::Array(vec![Value::String("^DIRECT DEBIT RECEIVED - THANK YOU".to_string())]));
+    config.budgets.insert("grocery".to_string(), Value::Integer(600));
 
     let toml_text = toml::to_string(&config).unwrap();
     fs::write(perfidb_home_dir.join("auto_label_rules.toml.example"), toml_text).expect("Could not create auto_label_rules.toml.example");
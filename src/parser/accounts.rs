@@ -0,0 +1,13 @@
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::multispace1;
+use nom::combinator::opt;
+use nom::IResult;
+use nom::sequence::tuple;
+use crate::parser::Statement;
+
+/// Parse `ACCOUNTS` or `SHOW ACCOUNTS`
+pub(crate) fn parse_accounts(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = opt(tuple((tag_no_case("SHOW"), multispace1)))(input)?;
+    let (input, _) = tag_no_case("ACCOUNTS")(input)?;
+    Ok((input, Statement::ListAccounts))
+}
@@ -0,0 +1,25 @@
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::{multispace1, u64};
+use nom::combinator::opt;
+use nom::IResult;
+use nom::sequence::preceded;
+use crate::parser::Statement;
+
+/// Parse `LOG [count]` pattern.
+pub(crate) fn parse_log(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("LOG")(input)?;
+    let (input, count) = opt(preceded(multispace1, u64))(input)?;
+    Ok((input, Statement::Log(count.map(|c| c as usize))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::audit::parse_log;
+    use crate::parser::Statement;
+
+    #[test]
+    fn test() {
+        assert_eq!(parse_log("log"), Ok(("", Statement::Log(None))));
+        assert_eq!(parse_log("LOG 5"), Ok(("", Statement::Log(Some(5)))));
+    }
+}
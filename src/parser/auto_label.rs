@@ -1,33 +1,50 @@
+use nom::branch::alt;
 use nom::bytes::complete::tag_no_case;
 use nom::character::complete::{multispace0, multispace1};
-use nom::combinator::opt;
+use nom::combinator::{opt, value};
 use nom::IResult;
+use nom::sequence::tuple;
+use crate::db::label_op::LabelCommand;
 use crate::parser::condition::where_parser;
 use crate::parser::Statement;
 
+/// AUTO_LABEL [RUN] WHERE ... [ONLY UNLABELLED | KEEP LABELS]
 pub(crate) fn auto_label(input: &str) -> IResult<&str, Statement> {
     let (input, _) = tag_no_case("AUTO_LABEL")(input)?;
     let (input, _) = multispace1(input)?;
     let (input, run) = opt(tag_no_case("RUN"))(input)?;
     let (input, _) = multispace0(input)?;
     let (input, condition) = where_parser(input)?;
+    let (input, label_cmd) = opt(alt((
+        value(LabelCommand::AutoUnlabelled, tuple((tag_no_case("ONLY"), multispace1, tag_no_case("UNLABELLED")))),
+        value(LabelCommand::AutoAdd, tuple((tag_no_case("KEEP"), multispace1, tag_no_case("LABELS")))),
+    )))(input)?;
 
-    Ok((input, Statement::AutoLabel(condition, run.is_some())))
+    Ok((input, Statement::AutoLabel(condition, run.is_some(), label_cmd.unwrap_or(LabelCommand::Auto))))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::{Condition, Statement};
+    use crate::db::label_op::LabelCommand;
+    use crate::parser::{Condition, Operator, Statement};
     use crate::parser::auto_label::auto_label;
 
     #[test]
     fn test() {
         let query = "auto_label run where id = 3";
         let result = auto_label(query);
-        assert_eq!(result, Ok(("", Statement::AutoLabel(Condition::Id(3), true))));
+        assert_eq!(result, Ok(("", Statement::AutoLabel(Condition::Id(3), true, LabelCommand::Auto))));
 
         let query = "auto_label where id = 3";
         let result = auto_label(query);
-        assert_eq!(result, Ok(("", Statement::AutoLabel(Condition::Id(3), false))));
+        assert_eq!(result, Ok(("", Statement::AutoLabel(Condition::Id(3), false, LabelCommand::Auto))));
+
+        let query = "auto_label run where date >= 2023-01-01 only unlabelled";
+        let result = auto_label(query);
+        assert!(matches!(result, Ok(("", Statement::AutoLabel(Condition::Date(Operator::GtEq, _), true, LabelCommand::AutoUnlabelled)))));
+
+        let query = "AUTO_LABEL RUN WHERE id = 3 KEEP LABELS";
+        let result = auto_label(query);
+        assert_eq!(result, Ok(("", Statement::AutoLabel(Condition::Id(3), true, LabelCommand::AutoAdd))));
     }
 }
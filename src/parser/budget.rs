@@ -0,0 +1,29 @@
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::multispace1;
+use nom::combinator::opt;
+use nom::IResult;
+use nom::sequence::preceded;
+use crate::parser::condition::month;
+use crate::parser::Statement;
+use crate::util::current_month;
+
+/// Parse `BUDGET [month]` pattern. Month can be in format 'yyyy-mm' or just a single int, same as `WHERE month = ...`.
+/// Default is current month.
+pub(crate) fn parse_budget(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("BUDGET")(input)?;
+    let (input, month) = opt(preceded(multispace1, month))(input)?;
+    Ok((input, Statement::Budget(month.unwrap_or_else(current_month))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::budget::parse_budget;
+    use crate::parser::Statement;
+    use crate::util::{current_month, year_month_of};
+
+    #[test]
+    fn test() {
+        assert_eq!(parse_budget("budget"), Ok(("", Statement::Budget(current_month()))));
+        assert_eq!(parse_budget("BUDGET 2023-07"), Ok(("", Statement::Budget(year_month_of(2023, 7)))));
+    }
+}
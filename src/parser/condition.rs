@@ -2,20 +2,22 @@ use std::ops::{Range};
 use chrono::{Duration, NaiveDate};
 use log::warn;
 use nom::branch::alt;
-use nom::bytes::complete::{is_not, tag, tag_no_case, take_till};
+use nom::bytes::complete::{is_not, tag, tag_no_case};
 use nom::character::complete::{char, digit1, i32, multispace0, multispace1, u32};
 use nom::{IResult};
 use nom::error::ErrorKind;
-use nom::multi::many0;
-use nom::sequence::delimited;
-use crate::parser::{Condition, floating_point_num, LogicalOperator, Operator, yyyy_mm_dd_date};
-use crate::util::{month_of, year_of};
+use nom::multi::{many0, separated_list1};
+use nom::combinator::{cut, opt, recognize, value, verify};
+use nom::sequence::{delimited, tuple};
+use crate::parser::{AmountKind, comma, Condition, floating_point_num, LogicalOperator, Operator, yyyy_mm_dd_date};
+use crate::util::{month_of, quarter_of, year_month_of, year_of, year_quarter_of};
 
 /// WHERE ...
 pub(crate) fn where_parser(input: &str) -> IResult<&str, Condition> {
     let (input, _) = tag_no_case("WHERE")(input)?;
     let (input, _) = multispace1(input)?;
-    let (input, first_condition) = single_condition(input)?;
+    // A condition must follow WHERE, otherwise the statement would run as if there was no WHERE
+    let (input, first_condition) = cut(single_condition)(input)?;
 
     // Followed by 0 or more AND/OR conditions
     match many0(alt((and_condition, or_condition)))(input) {
@@ -43,18 +45,34 @@ fn combine_logical_conditions(first: Condition, logical_conditions: Vec<(Logical
 }
 
 fn single_condition(input: &str) -> IResult<&str, Condition> {
+    // NOT only applies to the condition right after it, i.e. NOT month = 12 AND label = 'food' is (NOT month = 12) AND label = 'food'
+    let (input, not) = opt(tuple((tag_no_case("NOT"), multispace1)))(input)?;
     let (input, condition) = alt((
         where_id,
+        where_percent_of_max,
         where_spending,
         where_income,
         where_amount,
+        where_credit,
+        where_debit,
+        where_whole,
+        where_cents,
+        where_description_length,
         where_description,
         where_date,
+        where_imported,
         where_month,
+        where_quarter,
         where_year,
-        where_label))(input)?;
+        where_label_count,
+        where_label_has,
+        where_label,
+        where_account))(input)?;
     let (input, _) = multispace0(input)?;
-    Ok((input, condition))
+    match not {
+        Some(_) => Ok((input, Condition::Not(Box::new(condition)))),
+        None => Ok((input, condition))
+    }
 }
 
 /// AND single_condition
@@ -71,9 +89,13 @@ fn or_condition(input: &str) -> IResult<&str, (LogicalOperator, Condition)> {
     single_condition(input).map(|(input, c)|(input, (LogicalOperator::Or, c)))
 }
 
-/// id = 123
+/// id = 123   id IN (1, 2, 3)
 fn where_id(input: &str) -> IResult<&str, Condition> {
     let (input, _) = tag_no_case("id")(input)?;
+    alt((where_id_eq, where_id_in))(input)
+}
+
+fn where_id_eq(input: &str) -> IResult<&str, Condition> {
     let (input, _) = multispace0(input)?;
     let (input, _) = tag("=")(input)?;
     let (input, _) = multispace0(input)?;
@@ -81,44 +103,134 @@ fn where_id(input: &str) -> IResult<&str, Condition> {
     Ok((input, Condition::Id(id)))
 }
 
+fn where_id_in(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("IN")(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, ids) = delimited(tuple((char('('), multispace0)), separated_list1(comma, u32), tuple((multispace0, char(')'))))(input)?;
+    Ok((input, Condition::IdIn(ids)))
+}
+
+/// spending > 50% max   income >= 10% max   amount < 20% max
+fn where_percent_of_max(input: &str) -> IResult<&str, Condition> {
+    let (input, kind) = alt((tag_no_case("spending"), tag_no_case("income"), tag_no_case("amount")))(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, compare_operator) = amount_operator(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, percent) = floating_point_num(input)?;
+    let (input, _) = char('%')(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag_no_case("max")(input)?;
+
+    let kind = match kind.to_ascii_lowercase().as_str() {
+        "spending" => AmountKind::Spending,
+        "income" => AmountKind::Income,
+        _ => AmountKind::Amount,
+    };
+    Ok((input, Condition::PercentOfMax(kind, compare_operator, percent)))
+}
+
 /// spending > 100.0
 fn where_spending(input: &str) -> IResult<&str, Condition> {
     let (input, _) = tag_no_case("spending")(input)?;
     let (input, _) = multispace0(input)?;
-    let (input, compare_operator) = take_till(|c| c != '<' && c != '>' && c != '=')(input)?;
+    let (input, compare_operator) = amount_operator(input)?;
     let (input, _) = multispace0(input)?;
     let (input, value) = floating_point_num(input)?;
-    Ok((input, Condition::Spending(compare_operator.into(), value)))
+    Ok((input, Condition::Spending(compare_operator, value)))
 }
 
 /// income > 100.0
 fn where_income(input: &str) -> IResult<&str, Condition> {
     let (input, _) = tag_no_case("income")(input)?;
     let (input, _) = multispace0(input)?;
-    let (input, compare_operator) = take_till(|c| c != '<' && c != '>' && c != '=')(input)?;
+    let (input, compare_operator) = amount_operator(input)?;
     let (input, _) = multispace0(input)?;
     let (input, value) = floating_point_num(input)?;
-    Ok((input, Condition::Income(compare_operator.into(), value)))
+    Ok((input, Condition::Income(compare_operator, value)))
 }
 
 /// amount < -100.0
+/// abs(amount) > 100.0
+/// turnover > 100.0, same as abs(amount)
 fn where_amount(input: &str) -> IResult<&str, Condition> {
-    let (input, _) = tag_no_case("amount")(input)?;
+    let (input, abs) = alt((
+        value(true, tuple((tag_no_case("abs("), multispace0, tag_no_case("amount"), multispace0, char(')')))),
+        value(true, tag_no_case("turnover")),
+        value(false, tag_no_case("amount"))
+    ))(input)?;
     let (input, _) = multispace0(input)?;
-    let (input, compare_operator) = take_till(|c| c != '<' && c != '>' && c != '=')(input)?;
+    let (input, compare_operator) = amount_operator(input)?;
     let (input, _) = multispace0(input)?;
     let (input, value) = floating_point_num(input)?;
-    Ok((input, Condition::Amount(compare_operator.into(), value)))
+    if abs {
+        Ok((input, Condition::AbsAmount(compare_operator, value)))
+    } else {
+        Ok((input, Condition::Amount(compare_operator, value)))
+    }
+}
+
+/// credit, shortcut of 'income > 0'
+fn where_credit(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = tag_no_case("credit")(input)?;
+    Ok((input, Condition::Income(Operator::Gt, 0.0)))
+}
+
+/// whole, amount without cents
+fn where_whole(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = tag_no_case("whole")(input)?;
+    Ok((input, Condition::WholeAmount(true)))
+}
+
+/// cents = 0   cents != 0
+fn where_cents(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = tag_no_case("cents")(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, op) = alt((label_eq_operator, label_not_eq_operator))(input)?;
+    let (input, _) = char('0')(input)?;
+    Ok((input, Condition::WholeAmount(op == Operator::Eq)))
+}
+
+/// debit, shortcut of 'spending > 0'
+fn where_debit(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = tag_no_case("debit")(input)?;
+    Ok((input, Condition::Spending(Operator::Gt, 0.0)))
 }
 
-/// description|desc =|like|match '...'
+/// description|desc =|like|match|regex|starts with|ends with '...'
+/// desc like any ('uber', 'lyft')   desc like all ('uber', 'eats')
 fn where_description(input: &str) -> IResult<&str, Condition> {
     let (input, _) = alt((tag_description_multispace1, tag_desc_multispace1))(input)?;
-    let (input, operator) = alt((label_eq_operator, tag_like_operator, tag_match_operator))(input)?;
+    let (input, operator) = alt((label_eq_operator, tag_like_operator, tag_match_operator, tag_regex_operator, starts_with_operator, ends_with_operator))(input)?;
+    if operator == Operator::Match {
+        if let Ok((input, (any, texts))) = description_texts(input) {
+            let condition = if any {
+                Condition::DescriptionAny(texts)
+            } else {
+                texts.into_iter()
+                    .map(|text| Condition::Description(Operator::Match, text))
+                    .reduce(|cond1, cond2| Condition::from_logical(&LogicalOperator::And, cond1, cond2))
+                    .unwrap()
+            };
+            return Ok((input, condition));
+        }
+    }
     let (input, text) = delimited(char('\''), is_not("'"), char('\''))(input)?;
     Ok((input, Condition::Description(operator, text.into())))
 }
 
+/// any ('...', '...')   all ('...', '...'), returns true for any
+fn description_texts(input: &str) -> IResult<&str, (bool, Vec<String>)> {
+    let (input, any) = alt((value(true, tag_no_case("any")), value(false, tag_no_case("all"))))(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, texts) = delimited(
+        tuple((char('('), multispace0)),
+        separated_list1(comma, delimited(char('\''), is_not("'"), char('\''))),
+        tuple((multispace0, char(')')))
+    )(input)?;
+    Ok((input, (any, texts.into_iter().map(String::from).collect())))
+}
+
 /// 'description '
 fn tag_description_multispace1(input: &str) -> IResult<&str, ()> {
     let (input, _) = tag_no_case("description")(input)?;
@@ -133,25 +245,51 @@ fn tag_desc_multispace1(input: &str) -> IResult<&str, ()> {
     Ok((input, ()))
 }
 
-/// date = ...
+/// date = 2023-01-01   date >= '2023-01-01'   date != 2023-01-01
 fn where_date(input: &str) -> IResult<&str, Condition> {
     let (input, _) = tag_no_case("date")(input)?;
     let (input, _) = multispace0(input)?;
-    let (input, operator) = label_eq_operator(input)?;
+    let (input, operator) = compare_operator(input)?;
     let (input, _) = multispace0(input)?;
-    let (input, date) = yyyy_mm_dd_date(input)?;
+    let (input, date) = alt((delimited(char('\''), yyyy_mm_dd_date, char('\'')), yyyy_mm_dd_date))(input)?;
     let (input, _) = multispace0(input)?;
-    Ok((input, Condition::Date(operator, date..date + Duration::days(1))))
+    let date_range = compare_date_range(&operator, date);
+    Ok((input, Condition::Date(operator, date_range)))
+}
+
+/// imported >= '2023-06-01'   imported_after '2023-06-01'
+fn where_imported(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = tag_no_case("imported")(input)?;
+    let (input, operator) = alt((
+        value(Operator::Gt, tuple((tag_no_case("_after"), multispace1))),
+        delimited(multispace0, compare_operator, multispace0),
+    ))(input)?;
+    let (input, date) = alt((delimited(char('\''), yyyy_mm_dd_date, char('\'')), yyyy_mm_dd_date))(input)?;
+    let (input, _) = multispace0(input)?;
+    let date_range = compare_date_range(&operator, date);
+    Ok((input, Condition::Imported(operator, date_range)))
+}
+
+/// Comparisons are open-ended ranges, so they can be looked up in date index the same way as '='
+fn compare_date_range(operator: &Operator, date: NaiveDate) -> Range<NaiveDate> {
+    let next_day = date + Duration::days(1);
+    match operator {
+        Operator::Gt => next_day..NaiveDate::MAX,
+        Operator::GtEq => date..NaiveDate::MAX,
+        Operator::Lt => NaiveDate::MIN..date,
+        Operator::LtEq => NaiveDate::MIN..next_day,
+        _ => date..next_day,
+    }
 }
 
 /// month = ...
 fn where_month(input: &str) -> IResult<&str, Condition> {
     let (input, _) = tag_no_case("month")(input)?;
     let (input, _) = multispace0(input)?;
-    let (input, operator) = alt((label_eq_operator, between_operator))(input)?;
+    let (input, operator) = alt((label_eq_operator, label_not_eq_operator, between_operator))(input)?;
     let (input, date_range) = match operator {
         Operator::Between => month_range(input)?,
-        Operator::Eq => month(input)?,
+        Operator::Eq | Operator::NotEq => month(input)?,
         _ => {
             return Err(nom::Err::Error(nom::error::Error::new(input, ErrorKind::Fail)));
         }
@@ -161,19 +299,64 @@ fn where_month(input: &str) -> IResult<&str, Condition> {
     Ok((input, Condition::Date(operator, date_range)))
 }
 
-/// year = ...
+/// quarter = 2023-Q1   quarter = 1   quarter != 1
+fn where_quarter(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = tag_no_case("quarter")(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, operator) = alt((label_eq_operator, label_not_eq_operator))(input)?;
+    let (input, date_range) = alt((quarter_yyyy_q, quarter_int))(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, Condition::Date(operator, date_range)))
+}
+
+fn quarter_yyyy_q(input: &str) -> IResult<&str, Range<NaiveDate>> {
+    let (input, year) = i32(input)?;
+    let (input, _) = tag_no_case("-Q")(input)?;
+    let (input, quarter) = quarter_number(input)?;
+    Ok((input, year_quarter_of(year, quarter)))
+}
+
+fn quarter_int(input: &str) -> IResult<&str, Range<NaiveDate>> {
+    let (input, quarter) = quarter_number(input)?;
+    Ok((input, quarter_of(quarter)))
+}
+
+/// 1 to 4
+fn quarter_number(input: &str) -> IResult<&str, u32> {
+    let (remaining, quarter) = u32(input)?;
+    if (1..=4).contains(&quarter) {
+        Ok((remaining, quarter))
+    } else {
+        Err(nom::Err::Error(nom::error::Error::new(input, ErrorKind::Fail)))
+    }
+}
+
+/// year = 2023   year != 2023   year between 2021 and 2023
 fn where_year(input: &str) -> IResult<&str, Condition> {
     let (input, _) = tag_no_case("year")(input)?;
     let (input, _) = multispace0(input)?;
-    let (input, operator) = label_eq_operator(input)?;
+    let (input, operator) = alt((label_eq_operator, label_not_eq_operator, between_operator))(input)?;
     let (input, _) = multispace0(input)?;
-    let (input, year) = i32(input)?;
+    let (input, date_range) = match operator {
+        Operator::Between => year_range(input)?,
+        _ => i32(input).map(|(input, year)| (input, year_of(year)))?,
+    };
     let (input, _) = multispace0(input)?;
-    Ok((input, Condition::Date(operator, year_of(year))))
+    Ok((input, Condition::Date(operator, date_range)))
+}
+
+/// Both years are inclusive, i.e. from Jan 1 of the first year to Jan 1 after the last year
+fn year_range(input: &str) -> IResult<&str, Range<NaiveDate>> {
+    let (input, year_from) = i32(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("and")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, year_to) = i32(input)?;
+    Ok((input, year_of(year_from).start..year_of(year_to).end))
 }
 
 /// month can be in format 'yyyy-mm' or just a single int, e.g. 12.
-fn month(input: &str) -> IResult<&str, Range<NaiveDate>> {
+pub(crate) fn month(input: &str) -> IResult<&str, Range<NaiveDate>> {
     alt((month_yyyy_mm, month_int))(input)
 }
 
@@ -185,12 +368,7 @@ fn month_yyyy_mm(input: &str) -> IResult<&str, Range<NaiveDate>> {
     let year = year.to_string().parse::<i32>().unwrap();
     let month = month.to_string().parse::<u32>().unwrap();
 
-    let first_day = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
-    let next_month = if month == 12 { 1 } else { month + 1 };
-    let next_month_year = if month == 12 { year + 1 } else { year };
-    let first_day_next_month = NaiveDate::from_ymd_opt(next_month_year, next_month, 1).unwrap();
-
-    Ok((input, first_day..first_day_next_month))
+    Ok((input, year_month_of(year, month)))
 }
 
 fn month_int(input: &str) -> IResult<&str, Range<NaiveDate>> {
@@ -209,22 +387,87 @@ fn month_range(input: &str) -> IResult<&str, Range<NaiveDate>> {
     Ok((input, month_from.start..month_to.end))
 }
 
-/// label = ...   label IS NULL    label IS NOT NULL
+/// labels count > 3   tag_count = 0
+fn where_label_count(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = alt((
+        tag_no_case("tag_count"),
+        tag_no_case("label_count"),
+        recognize(tuple((alt((tag_no_case("labels"), tag_no_case("tags"))), multispace1, tag_no_case("count"))))
+    ))(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, operator) = compare_operator(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, count) = u32(input)?;
+    Ok((input, Condition::TagCount(operator, count as usize)))
+}
+
+/// desc_len = 0   desc_len > 100
+fn where_description_length(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = tag_no_case("desc_len")(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, operator) = compare_operator(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, length) = u32(input)?;
+    Ok((input, Condition::DescriptionLength(operator, length as usize)))
+}
+
+/// account = '...'   account != '...'
+fn where_account(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = tag_no_case("account")(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, op) = alt((label_eq_operator, label_not_eq_operator))(input)?;
+    let (input, account) = delimited(char('\''), is_not("'"), char('\''))(input)?;
+    Ok((input, Condition::Account(op, account.into())))
+}
+
+/// label = ...   label IS NULL    label IS NOT NULL    primary_label = ...
 fn where_label(input: &str) -> IResult<&str, Condition> {
+    let (input, primary) = opt(tag_no_case("primary_"))(input)?;
     let (input, _) = tag_no_case("label")(input)?;
     let (input, _) = multispace1(input)?;
-    let (input, op) = alt((label_eq_operator, label_not_eq_operator, label_is_null_operator, label_is_not_null_operator))(input)?;
+    if primary.is_some() {
+        let (input, op) = alt((label_eq_operator, label_not_eq_operator))(input)?;
+        let (input, label) = delimited(char('\''), is_not("'"), char('\''))(input)?;
+        return Ok((input, Condition::PrimaryLabel(op, label.trim().into())));
+    }
+
+    let (input, op) = alt((label_eq_operator, label_not_eq_operator, tag_like_operator, label_is_null_operator, label_is_not_null_operator))(input)?;
 
     // If we see 'IS NULL' or 'IS NOT NULL' there is no need to parse the labels, we just return empty string label here
     match op {
         Operator::IsNull | Operator::IsNotNull => Ok((input, Condition::Label(op, "".into()))),
         _ => {
-            let (input, labels) = delimited(char('\''), is_not("'"), char('\''))(input)?;
-            Ok((input, Condition::Label(op, labels.into())))
+            let (input, label) = delimited(char('\''), is_not("'"), char('\''))(input)?;
+            // Same as LABEL statement, whitespace around the label is ignored but spaces within are kept
+            Ok((input, Condition::Label(op, label.trim().into())))
         }
     }
 }
 
+/// label HAS ALL ('a', 'b')   label HAS ANY ('a', 'b')
+fn where_label_has(input: &str) -> IResult<&str, Condition> {
+    let (input, _) = tag_no_case("label")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("HAS")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, all) = alt((value(true, tag_no_case("ALL")), value(false, tag_no_case("ANY"))))(input)?;
+    let (input, _) = multispace0(input)?;
+    let label = delimited(char('\''), is_not("'"), char('\''));
+    let (input, labels) = delimited(tuple((char('('), multispace0)), separated_list1(comma, label), tuple((multispace0, char(')'))))(input)?;
+    let labels = labels.into_iter().map(|label: &str| label.trim().to_string()).collect();
+    Ok((input, if all { Condition::LabelHasAll(labels) } else { Condition::LabelHasAny(labels) }))
+}
+
+/// '=', '!=', '>', '>=', '<', '<='
+pub(crate) fn compare_operator(input: &str) -> IResult<&str, Operator> {
+    let (input, operator) = alt((tag(">="), tag("<="), tag("!="), tag("="), tag(">"), tag("<")))(input)?;
+    Ok((input, operator.into()))
+}
+
+/// Same as `compare_operator` except '!=', which amount conditions don't support
+fn amount_operator(input: &str) -> IResult<&str, Operator> {
+    verify(compare_operator, |op| *op != Operator::NotEq)(input)
+}
 
 /// '='
 fn label_eq_operator(input: &str) -> IResult<&str, Operator> {
@@ -268,6 +511,31 @@ fn tag_match_operator(input: &str) -> IResult<&str, Operator> {
     Ok((input, Operator::Match))
 }
 
+/// 'regex'
+fn tag_regex_operator(input: &str) -> IResult<&str, Operator> {
+    let (input, _) = tag_no_case("regex")(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, Operator::Regex))
+}
+
+/// 'starts with'
+fn starts_with_operator(input: &str) -> IResult<&str, Operator> {
+    let (input, _) = tag_no_case("starts")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("with")(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, Operator::StartsWith))
+}
+
+/// 'ends with'
+fn ends_with_operator(input: &str) -> IResult<&str, Operator> {
+    let (input, _) = tag_no_case("ends")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("with")(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, Operator::EndsWith))
+}
+
 /// 'between'
 fn between_operator(input: &str) -> IResult<&str, Operator> {
     let (input, _) = tag_no_case("between")(input)?;
@@ -279,8 +547,9 @@ fn between_operator(input: &str) -> IResult<&str, Operator> {
 #[cfg(test)]
 mod tests {
     use chrono::{Datelike, NaiveDate};
-    use crate::parser::{Condition, Operator};
+    use crate::parser::{AmountKind, Condition, Operator};
     use crate::parser::condition::where_parser;
+    use crate::util::{month_of, year_of};
 
     #[test]
     fn test() {
@@ -300,6 +569,29 @@ mod tests {
         let result = where_parser(query);
         assert_eq!(result, Ok(("", Condition::Description(Operator::Match, "abc".into()))));
 
+        let query = "where desc like any ('uber', 'lyft','taxi')";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::DescriptionAny(vec!["uber".into(), "lyft".into(), "taxi".into()]))));
+
+        let query = "where desc like ALL ( 'uber', 'eats' )";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::And(Box::new((
+            Condition::Description(Operator::Match, "uber".into()),
+            Condition::Description(Operator::Match, "eats".into())
+        ))))));
+
+        let query = "where desc regex '^UBER.*EATS'";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Description(Operator::Regex, "^UBER.*EATS".into()))));
+
+        let query = "where desc starts with 'POS '";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Description(Operator::StartsWith, "POS ".into()))));
+
+        let query = "where description ENDS WITH 'sydney'";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Description(Operator::EndsWith, "sydney".into()))));
+
         let query = "where month = 12";
         let result = where_parser(query).unwrap().1;
         assert!(matches!(result, Condition::Date { .. }));
@@ -315,10 +607,158 @@ mod tests {
             assert_eq!(date_range.end, NaiveDate::from_ymd_opt(2023, 5, 1).unwrap());
         }
 
+        let query = "where date = 2023-01-31";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Date(Operator::Eq, NaiveDate::from_ymd_opt(2023, 1, 31).unwrap()..NaiveDate::from_ymd_opt(2023, 2, 1).unwrap()))));
+
+        let query = "where date != 2023-01-31";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Date(Operator::NotEq, NaiveDate::from_ymd_opt(2023, 1, 31).unwrap()..NaiveDate::from_ymd_opt(2023, 2, 1).unwrap()))));
+
+        let query = "where date >= '2023-01-01'";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Date(Operator::GtEq, NaiveDate::from_ymd_opt(2023, 1, 1).unwrap()..NaiveDate::MAX))));
+
+        let query = "where date > 2023-01-01";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Date(Operator::Gt, NaiveDate::from_ymd_opt(2023, 1, 2).unwrap()..NaiveDate::MAX))));
+
+        let query = "where date < '2023-01-01'";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Date(Operator::Lt, NaiveDate::MIN..NaiveDate::from_ymd_opt(2023, 1, 1).unwrap()))));
+
+        let query = "where date <= 2023-01-01";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Date(Operator::LtEq, NaiveDate::MIN..NaiveDate::from_ymd_opt(2023, 1, 2).unwrap()))));
+
+        let query = "where spending >= 50% max";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::PercentOfMax(AmountKind::Spending, Operator::GtEq, 50.0))));
+
+        // Missing or invalid operator is a parse error
+        assert!(matches!(where_parser("where spending 50% max"), Err(nom::Err::Failure(_))));
+        assert!(where_parser("where income => 10% max").is_err());
+        assert!(where_parser("where spending != 50").is_err());
+
+        let query = "where labels count > 2";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::TagCount(Operator::Gt, 2))));
+
+        let query = "where desc_len > 100 or desc_len=0";
+        let result = where_parser(query).unwrap().1;
+        assert_eq!(result, Condition::Or(Box::new((Condition::DescriptionLength(Operator::Gt, 100), Condition::DescriptionLength(Operator::Eq, 0)))));
+
+        let query = "where tag_count != 0";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::TagCount(Operator::NotEq, 0))));
+
+        let query = "where year between 2021 and 2023 and spending > 10";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::And(Box::new((
+            Condition::Date(Operator::Between, NaiveDate::from_ymd_opt(2021, 1, 1).unwrap()..NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+            Condition::Spending(Operator::Gt, 10.0)
+        ))))));
+
+        // Both years are included
+        if let Condition::Date(_, date_range) = where_parser("where year between 2021 and 2023").unwrap().1 {
+            assert!(date_range.contains(&NaiveDate::from_ymd_opt(2021, 1, 1).unwrap()));
+            assert!(date_range.contains(&NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()));
+            assert!(!date_range.contains(&NaiveDate::from_ymd_opt(2020, 12, 31).unwrap()));
+            assert!(!date_range.contains(&NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()));
+        }
+
+        let query = "where year BETWEEN 2023 AND 2023";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Date(Operator::Between, year_of(2023)))));
+
+        let query = "where quarter = 2023-Q4";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Date(Operator::Eq, NaiveDate::from_ymd_opt(2023, 10, 1).unwrap()..NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()))));
+
+        let query = "where quarter = 2";
+        let result = where_parser(query).unwrap().1;
+        if let Condition::Date(_, date_range) = result {
+            assert_eq!(date_range.start.month(), 4);
+            assert_eq!(date_range.end.month(), 7);
+        }
+
+        assert!(where_parser("where quarter = 2023-Q5").is_err());
+
+        let query = "where NOT month = 12 and not label = 'food'";
+        let result = where_parser(query).unwrap().1;
+        assert_eq!(result, Condition::And(Box::new((
+            Condition::Not(Box::new(Condition::Date(Operator::Eq, month_of(12)))),
+            Condition::Not(Box::new(Condition::Label(Operator::Eq, "food".into())))
+        ))));
+
+        let june_1 = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap();
+        let query = "where imported >= '2023-06-01'";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Imported(Operator::GtEq, june_1..NaiveDate::MAX))));
+
+        let query = "where imported_after 2023-06-01 and label = 'abc'";
+        let result = where_parser(query).unwrap().1;
+        assert_eq!(result, Condition::And(Box::new((
+            Condition::Imported(Operator::Gt, june_1.succ_opt().unwrap()..NaiveDate::MAX),
+            Condition::Label(Operator::Eq, "abc".into())
+        ))));
+
+        let query = "where primary_label != ' grocery '";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::PrimaryLabel(Operator::NotEq, "grocery".into()))));
+        assert!(where_parser("where primary_label is null").is_err());
+
         let query = "where label = 'abc, def'";
         let result = where_parser(query);
         assert_eq!(result, Ok(("", Condition::Label(Operator::Eq, "abc, def".into()))));
 
+        let query = "where account = 'default' or account!='amex'";
+        let result = where_parser(query).unwrap().1;
+        assert_eq!(result, Condition::Or(Box::new((
+            Condition::Account(Operator::Eq, "default".into()),
+            Condition::Account(Operator::NotEq, "amex".into())
+        ))));
+
+        let query = "where id in (1, 20,3) and id = 4";
+        let result = where_parser(query).unwrap().1;
+        assert_eq!(result, Condition::And(Box::new((Condition::IdIn(vec![1, 20, 3]), Condition::Id(4)))));
+
+        let query = "where label != ' eating out '";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Label(Operator::NotEq, "eating out".into()))));
+
+        let query = "where abs(amount) >= 1000 or amount < 5";
+        let result = where_parser(query).unwrap().1;
+        assert_eq!(result, Condition::Or(Box::new((Condition::AbsAmount(Operator::GtEq, 1000.0), Condition::Amount(Operator::Lt, 5.0)))));
+        assert_eq!(where_parser("where turnover > 100"), Ok(("", Condition::AbsAmount(Operator::Gt, 100.0))));
+        assert!(where_parser("where amount 50").is_err());
+        assert!(where_parser("where abs(amount) 50").is_err());
+        assert!(where_parser("where turnover 100").is_err());
+
+        let query = "where label has all ('food', ' eating out') and label has any('a','b')";
+        let result = where_parser(query).unwrap().1;
+        assert_eq!(result, Condition::And(Box::new((
+            Condition::LabelHasAll(vec!["food".into(), "eating out".into()]),
+            Condition::LabelHasAny(vec!["a".into(), "b".into()])
+        ))));
+
+        let query = "where whole and cents != 0";
+        let result = where_parser(query).unwrap().1;
+        assert_eq!(result, Condition::And(Box::new((Condition::WholeAmount(true), Condition::WholeAmount(false)))));
+        assert_eq!(where_parser("where cents = 0"), Ok(("", Condition::WholeAmount(true))));
+        assert!(where_parser("where cents = 5").is_err());
+
+        let query = "where label like 'food'";
+        let result = where_parser(query);
+        assert_eq!(result, Ok(("", Condition::Label(Operator::Match, "food".into()))));
+
+
+        let query = "where debit and label = 'food' or credit";
+        let result = where_parser(query).unwrap().1;
+        assert_eq!(result, Condition::Or(Box::new((
+            Condition::And(Box::new((Condition::Spending(Operator::Gt, 0.0), Condition::Label(Operator::Eq, "food".into())))),
+            Condition::Income(Operator::Gt, 0.0)
+        ))));
 
         let query = "WHERE desc like 'abc' AND spending > 1000";
         let result = where_parser(query).unwrap().1;
@@ -1,17 +1,25 @@
 use log::warn;
+use nom::branch::alt;
 use nom::bytes::complete::tag_no_case;
+use nom::character::complete::multispace0;
+use nom::combinator::opt;
 use nom::IResult;
 use nom::multi::many1;
+use nom::sequence::preceded;
 use crate::parser::{space_comma1, Statement};
 
+/// DELETE 100 200 [(force)], `--force` is the same as `(force)`
 pub(crate) fn parse_delete(input: &str) -> IResult<&str, Statement> {
     let (input, _) = tag_no_case("DELETE")(input)?;
     let parse_result = many1(parse_transaction_id)(input);
     match parse_result {
-        Ok((input, trans_ids)) => Ok((input, Statement::Delete(Some(trans_ids)))),
+        Ok((input, trans_ids)) => {
+            let (input, force) = opt(preceded(multispace0, alt((tag_no_case("(force)"), tag_no_case("--force")))))(input)?;
+            Ok((input, Statement::Delete(Some(trans_ids), force.is_some())))
+        },
         Err(e) => {
             warn!("{e:?}");
-            Ok((input, Statement::Delete(None)))
+            Ok((input, Statement::Delete(None, false)))
         }
     }
 
@@ -22,3 +30,16 @@ fn parse_transaction_id(input: &str) -> IResult<&str, u32> {
     let (input, trans_id) = nom::character::complete::u32(input)?;
     Ok((input, trans_id))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::delete::parse_delete;
+    use crate::parser::Statement;
+
+    #[test]
+    fn test() {
+        assert_eq!(parse_delete("DELETE 1, 2 3"), Ok(("", Statement::Delete(Some(vec![1, 2, 3]), false))));
+        assert_eq!(parse_delete("delete 1 2 (force)"), Ok(("", Statement::Delete(Some(vec![1, 2]), true))));
+        assert_eq!(parse_delete("delete 1 --FORCE"), Ok(("", Statement::Delete(Some(vec![1]), true))));
+    }
+}
@@ -0,0 +1,30 @@
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag_no_case};
+use nom::character::complete::{char, multispace1};
+use nom::IResult;
+use nom::sequence::delimited;
+use crate::parser::{non_space1, Statement};
+
+/// Parse `DIFF 'file_path'` pattern.
+pub(crate) fn parse_diff(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("DIFF")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, file_path) = alt((
+        delimited(char('\''), is_not("'"), char('\'')),
+        delimited(char('"'), is_not("\""), char('"')),
+        non_space1
+    ))(input)?;
+    Ok((input, Statement::Diff(file_path.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::diff::parse_diff;
+    use crate::parser::Statement;
+
+    #[test]
+    fn test() {
+        assert_eq!(parse_diff("DIFF 'amex/2023 03.csv'"), Ok(("", Statement::Diff("amex/2023 03.csv".into()))));
+        assert_eq!(parse_diff("diff ./2023-03.csv"), Ok(("", Statement::Diff("./2023-03.csv".into()))));
+    }
+}
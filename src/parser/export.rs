@@ -1,14 +1,44 @@
-use nom::bytes::complete::tag_no_case;
-use nom::character::complete::multispace1;
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag_no_case};
+use nom::character::complete::{char, multispace0, multispace1};
+use nom::combinator::opt;
 use nom::IResult;
-use crate::parser::Statement;
+use nom::sequence::delimited;
+use crate::parser::{non_space1, Statement};
+use crate::parser::condition::where_parser;
+use crate::parser::select::group_by;
 
-/// Parse `EXPORT TO file_path` pattern.
+/// Parse `EXPORT TO file_path [WHERE ...] [GROUP BY label]` pattern.
 pub(crate) fn export(input: &str) -> IResult<&str, Statement> {
     let (input, _) = tag_no_case("EXPORT")(input)?;
     let (input, _) =  multispace1(input)?;
     let (input, _) = tag_no_case("TO")(input)?;
-    let (file_path, _) =  multispace1(input)?;
-    let quotation_marks :&[_] = &['\'', '"'];
-    Ok((file_path, Statement::Export(file_path.trim_matches(quotation_marks).to_string())))
+    let (input, _) =  multispace1(input)?;
+    let (input, file_path) = alt((
+        delimited(char('\''), is_not("'"), char('\'')),
+        delimited(char('"'), is_not("\""), char('"')),
+        non_space1
+    ))(input)?;
+    let (input, _) =  multispace0(input)?;
+    let (input, condition) = opt(where_parser)(input)?;
+    let (input, _) =  multispace0(input)?;
+    let (input, group_by) = opt(group_by)(input)?;
+    Ok((input, Statement::Export(file_path.to_string(), condition, group_by)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{Condition, GroupBy, GroupOrder, LabelAmount, Operator, Statement};
+    use crate::parser::export::export;
+
+    #[test]
+    fn test() {
+        let query = "EXPORT  to './finance/export.csv'";
+        let result = export(query);
+        assert_eq!(result, Ok(("", Statement::Export("./finance/export.csv".into(), None, None))));
+
+        let query = "export to summary.csv where spending > 0 group by label";
+        let result = export(query);
+        assert_eq!(result, Ok(("", Statement::Export("summary.csv".into(), Some(Condition::Spending(Operator::Gt, 0.0)), Some(GroupBy::Label(LabelAmount::Full, GroupOrder::Amount { desc: true }))))));
+    }
 }
@@ -5,7 +5,7 @@ use nom::{IResult};
 use nom::sequence::delimited;
 use crate::parser::{Statement};
 
-/// Parse `IMPORT (inverse dryrun)
+/// Parse `IMPORT (inverse dryrun autolabel account=name)
 pub(crate) fn import(input: &str) -> IResult<&str, Statement> {
     let (input, _) = tag_no_case("IMPORT")(input)?;
     let (input, _) =  multispace0(input)?;
@@ -13,17 +13,23 @@ pub(crate) fn import(input: &str) -> IResult<&str, Statement> {
 
     let mut inverse_flag = false;
     let mut dryrun_flag = false;
+    let mut autolabel_flag = false;
+    let mut account = None;
     if let Some(import_options) = import_options {
         for import_option in import_options.split(&[' ', ',']) {
             if import_option == "i" || import_option == "inverse" {
                 inverse_flag = true;
             } else if import_option == "dryrun" {
                 dryrun_flag = true;
+            } else if import_option == "autolabel" {
+                autolabel_flag = true;
+            } else if let Some(name) = import_option.strip_prefix("account=") {
+                account = Some(name.to_string());
             }
         }
     }
 
-    Ok((input, Statement::Import(inverse_flag, dryrun_flag)))
+    Ok((input, Statement::Import(inverse_flag, dryrun_flag, autolabel_flag, account)))
 }
 
 fn parse_import_options(input: &str) -> IResult<&str, Option<&str>> {
@@ -1,10 +1,14 @@
+use chrono::NaiveDateTime;
+use log::warn;
 use nom::bytes::complete::{is_not, tag_no_case};
+use nom::InputTakeAtPosition;
 use nom::character::complete::{char, multispace0, multispace1};
-use nom::combinator::opt;
+use nom::error::{Error, ErrorKind};
+use nom::combinator::{cut, eof, opt};
 use nom::IResult;
 use nom::multi::many1;
-use nom::sequence::delimited;
-use crate::csv_reader::Record;
+use nom::sequence::{delimited, preceded};
+use crate::csv_reader::{DATETIME_FORMAT, Record};
 use crate::parser::{comma, floating_point_num, non_space, Statement, yyyy_mm_dd_date};
 
 pub(crate) fn parse_insert(input: &str) -> IResult<&str, Statement> {
@@ -14,6 +18,9 @@ pub(crate) fn parse_insert(input: &str) -> IResult<&str, Statement> {
     let (input, _) = tag_no_case("VALUES")(input)?;
     let (input, _) =  multispace0(input)?;
     let (input, records) = many1(parse_record)(input)?;
+    // Anything left is a malformed record, fail the statement rather than inserting only the records before it
+    let (input, _) = multispace0(input)?;
+    let (input, _) = cut(eof)(input)?;
     Ok((input, Statement::Insert(account, records)))
 }
 
@@ -34,31 +41,63 @@ fn parse_record(input: &str) -> IResult<&str, Record> {
 
 fn parse_record_inner(input: &str) -> IResult<&str, Record> {
     let (input, _) = multispace0(input)?;
-    let (input, date) = yyyy_mm_dd_date(input)?;
+    let (input, date) = parse_record_datetime(input)?;
     let (input, _) = comma(input)?;
     let (input, desc) = delimited(char('\''), is_not("'"), char('\''))(input)?;
     let (input, _) = comma(input)?;
     let (input, amount) = floating_point_num(input)?;
     let (input, labels) = opt(parse_record_labels)(input)?;
+    let (input, account) = opt(parse_record_token)(input)?;
+    let (input, currency) = opt(parse_record_token)(input)?;
+    let (input, _) = multispace0(input)?;
 
     Ok((input, Record {
         id: None,
-        account: "".to_string(),
-        date: date.and_hms_opt(0, 0, 0).unwrap(),
+        // Empty account means the account is not specified in this row
+        account: account.unwrap_or_default(),
+        date,
         description: desc.into(),
         amount,
         labels,
+        currency: currency.map(|c| c.to_uppercase()),
+        balance: None,
+        raw_amount: None,
     }))
 }
 
+/// Parse date with optional time, e.g. 2023-02-21 or 2023-02-21T14:30:00. Time is midnight if omitted.
+/// A malformed time is a failure rather than an error, so the statement is rejected instead of skipping the record.
+fn parse_record_datetime(input: &str) -> IResult<&str, NaiveDateTime> {
+    let (input, date) = yyyy_mm_dd_date(input)?;
+    let (input, time) = opt(preceded(char('T'), is_not(", \t)")))(input)?;
+    match time {
+        None => Ok((input, date.and_hms_opt(0, 0, 0).unwrap())),
+        Some(time) => match NaiveDateTime::parse_from_str(&format!("{date}T{time}"), DATETIME_FORMAT) {
+            Ok(datetime) => Ok((input, datetime)),
+            Err(e) => {
+                warn!("{e:?}");
+                Err(nom::Err::Failure(Error::new(input, ErrorKind::Fail)))
+            }
+        }
+    }
+}
+
 /// Parse additional labels argument in VALUES ( ... )
 fn parse_record_labels(input: &str) -> IResult<&str, Vec<String>> {
     let (input, _) = comma(input)?;
     let (input, labels) = delimited(char('\''), is_not("'"), char('\''))(input)?;
-    let labels = labels.split(&[',', ' ']).filter(|s| !s.is_empty()).map(str::to_string).collect::<Vec<String>>();
+    // Labels are separated by comma, so a label can contain spaces, e.g. 'eating out, food'
+    let labels = labels.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect::<Vec<String>>();
     Ok((input, labels))
 }
 
+/// Parse additional unquoted account or currency argument in VALUES ( ... )
+fn parse_record_token(input: &str) -> IResult<&str, String> {
+    let (input, _) = comma(input)?;
+    let (input, token) = input.split_at_position1_complete(|c| c.is_whitespace() || c == ',' || c == ')', nom::error::ErrorKind::Fail)?;
+    Ok((input, token.into()))
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::NaiveDate;
@@ -75,5 +114,35 @@ mod tests {
             assert_eq!(records[0].labels, Some(vec!["dining".to_string(), "lunch".to_string()]));
             assert_eq!(records[1].date.date(), NaiveDate::from_ymd_opt(2022, 1, 20).unwrap());
         }
+
+        let statement = "INSERT VALUES (2020-11-03, 'food', -30.45, 'eating out,lunch ')";
+        if let Statement::Insert(_, records) = parse_insert(statement).unwrap().1 {
+            assert_eq!(records[0].labels, Some(vec!["eating out".to_string(), "lunch".to_string()]));
+        }
+
+        let statement = "INSERT VALUES (2020-11-03T09:15:30, 'coffee', -4.5)";
+        let result = parse_insert(statement).unwrap().1;
+        if let Statement::Insert(_, records) = result {
+            assert_eq!(records[0].date, NaiveDate::from_ymd_opt(2020, 11, 3).unwrap().and_hms_opt(9, 15, 30).unwrap());
+        }
+
+        // A malformed time fails the whole statement, instead of inserting only the rows before it
+        let statement = "INSERT VALUES (2020-11-03T09:15:30, 'coffee', -4.5), (2020-11-03T9:15, 'coffee', -4.5)";
+        assert!(matches!(parse_insert(statement), Err(nom::Err::Failure(_))));
+
+        let statement = "INSERT VALUES (2020-11-03, 'coffee', -4.5), (2020-11-03, 'tea', abc)";
+        assert!(matches!(parse_insert(statement), Err(nom::Err::Failure(_))));
+
+        let statement = "INSERT INTO cba VALUES (2020-11-03, 'food', -30.45, 'dining', amex, usd), (2022-01-20, 'computer', -2000, amex), (2022-01-21, 'rent', -500)";
+        let result = parse_insert(statement).unwrap().1;
+        if let Statement::Insert(account, records) = result {
+            assert_eq!(account, Some("cba".to_string()));
+            assert_eq!(records[0].account, "amex");
+            assert_eq!(records[0].currency, Some("USD".to_string()));
+            assert_eq!(records[1].labels, None);
+            assert_eq!(records[1].account, "amex");
+            assert_eq!(records[1].currency, None);
+            assert_eq!(records[2].account, "");
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,30 @@
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::{multispace0, multispace1};
+use nom::IResult;
+use nom::sequence::tuple;
+use crate::parser::condition::compare_operator;
+use crate::parser::{floating_point_num, Statement};
+
+/// Parse `MERCHANTS WHERE spending > 100 PER MONTH` pattern.
+pub(crate) fn parse_merchants(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tuple((tag_no_case("MERCHANTS"), multispace1, tag_no_case("WHERE"), multispace1, tag_no_case("spending")))(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, operator) = compare_operator(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, threshold) = floating_point_num(input)?;
+    let (input, _) = tuple((multispace1, tag_no_case("PER"), multispace1, tag_no_case("MONTH")))(input)?;
+    Ok((input, Statement::Merchants(operator, threshold)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::merchants::parse_merchants;
+    use crate::parser::{Operator, Statement};
+
+    #[test]
+    fn test() {
+        assert_eq!(parse_merchants("MERCHANTS WHERE spending > 100 PER MONTH"), Ok(("", Statement::Merchants(Operator::Gt, 100.0))));
+        assert_eq!(parse_merchants("merchants where spending>=50.5 per month"), Ok(("", Statement::Merchants(Operator::GtEq, 50.5))));
+        assert!(parse_merchants("MERCHANTS WHERE spending > 100").is_err());
+    }
+}
@@ -0,0 +1,36 @@
+use nom::bytes::complete::{is_not, tag_no_case};
+use nom::character::complete::{char, multispace0, multispace1};
+use nom::IResult;
+use nom::multi::many1;
+use nom::sequence::{delimited, preceded};
+use crate::parser::{space_comma1, Statement};
+
+/// Parse `MERGE LABEL 'a' 'b' INTO 'c'` pattern.
+pub(crate) fn parse_merge_label(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("MERGE")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("LABEL")(input)?;
+    let (input, sources) = many1(preceded(space_comma1, quoted_label))(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("INTO")(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, target) = quoted_label(input)?;
+    Ok((input, Statement::MergeLabel(sources, target)))
+}
+
+fn quoted_label(input: &str) -> IResult<&str, String> {
+    let (input, label) = delimited(char('\''), is_not("'"), char('\''))(input)?;
+    Ok((input, label.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::merge::parse_merge_label;
+    use crate::parser::Statement;
+
+    #[test]
+    fn test() {
+        assert_eq!(parse_merge_label("merge label 'coffee', 'cafe' 'coffees' into 'coffee'"),
+                   Ok(("", Statement::MergeLabel(vec!["coffee".into(), "cafe".into(), "coffees".into()], "coffee".into()))));
+    }
+}
@@ -6,6 +6,22 @@ mod condition;
 mod insert;
 mod delete;
 mod auto_label;
+mod audit;
+mod vacuum;
+mod budget;
+mod similar;
+mod suggest;
+mod merge;
+mod move_account;
+mod replay;
+mod save;
+mod reconcile;
+mod reload;
+mod merchants;
+mod view;
+mod diff;
+mod accounts;
+mod rollback;
 
 use std::ops::Range;
 use chrono::NaiveDate;
@@ -19,61 +35,202 @@ use nom::error::{Error, ErrorKind};
 use crate::csv_reader::Record;
 use crate::db::label_op::{LabelCommand};
 
+/// Keywords of perfidb SQL dialect, used by the REPL for completion
+pub(crate) const KEYWORDS: &[&str] = &[
+    "SELECT", "FROM", "WHERE", "AND", "OR", "ORDER BY", "GROUP BY", "LIMIT", "DESC", "TOP", "BOTTOM",
+    "LABEL", "AUTO_LABEL", "RUN", "IMPORT", "EXPORT", "TO", "INSERT", "INTO", "VALUES", "DELETE", "LOG", "VACUUM", "BUDGET", "SIMILAR", "SUGGEST", "MERGE", "MOVE", "REPLAY", "SAVE", "RECONCILE", "RELOAD RULES", "MERCHANTS", "PER MONTH", "CREATE VIEW", "VIEW", "AS", "DIFF", "ACCOUNTS", "SHOW ACCOUNTS", "ROLLBACK LAST IMPORT", "ONLY UNLABELLED", "KEEP LABELS",
+    "SUM", "COUNT", "DISTINCT", "NET", "AUTO()", "SPENDING", "INCOME", "CREDIT", "DEBIT", "WHOLE", "CENTS", "AMOUNT", "DESCRIPTION", "DESC_LEN", "DATE", "MONTH", "QUARTER", "YEAR", "ID",
+    "LIKE", "MATCH", "REGEX", "STARTS WITH", "ENDS WITH", "BETWEEN", "IN", "HAS ALL", "HAS ANY", "IS NULL", "IS NOT NULL",
+];
+
 #[derive(Debug, PartialEq)]
 pub(crate) enum Statement {
-    /// SELECT statement (projection, account, where clause, order by, limit, group by)
-    Select(Projection, Option<String>, Option<Condition>, OrderBy, Option<usize>, Option<GroupBy>),
+    /// SELECT statement (projection, accounts, where clause, order by, limit, group by)
+    Select(Projection, Option<Vec<String>>, Option<Condition>, OrderBy, Option<usize>, Option<GroupBy>),
 
     /// LABEL 100 200 : food -grocery
     Label(Vec<u32>, LabelCommand),
     
-    /// AUTO_LABEL [RUN] WHERE ...
+    /// AUTO_LABEL [RUN] WHERE ... [ONLY UNLABELLED | KEEP LABELS]
     /// The second bool indicates if it should actually run auto_label operation.
     /// If 'RUN' is specified it will be true.
-    AutoLabel(Condition, bool),
+    /// The label command is one of the auto labelling commands, depending on the modifier after WHERE clause.
+    AutoLabel(Condition, bool, LabelCommand),
 
-    /// EXPORT TO file_path
-    Export(String),
+    /// EXPORT TO file_path (file path, where clause, group by)
+    Export(String, Option<Condition>, Option<GroupBy>),
 
-    /// IMPORT account FROM file_path
-    Import(bool, bool),
+    /// IMPORT (inverse, dryrun, autolabel, account=name)
+    Import(bool, bool, bool, Option<String>),
 
     /// INSERT INTO account VALUES (2022-05-20, 'description', -30.0, 'label1, label2'), (2022-05-21, 'description', -32.0)
     Insert(Option<String>, Vec<Record>),
 
-    /// DELETE trans_id
-    Delete(Option<Vec<u32>>),
+    /// DELETE trans_id [(force)], the bool indicates if it deletes without asking for confirmation
+    Delete(Option<Vec<u32>>, bool),
+
+    /// LOG [count], show most recent entries of audit log
+    Log(Option<usize>),
+
+    /// VACUUM, remove empty index entries from database file
+    Vacuum,
+
+    /// BUDGET [month], compare spending of each label against its monthly budget
+    Budget(Range<NaiveDate>),
+
+    /// SIMILAR trans_id [LIMIT n], find transactions with similar description
+    Similar(u32, Option<usize>),
+
+    /// SUGGEST trans_id, suggest labels based on similar labelled transactions
+    Suggest(u32),
+
+    /// MERGE LABEL 'a' 'b' INTO 'c'
+    MergeLabel(Vec<String>, String),
+
+    /// MOVE 100 200 TO account, or MOVE TO account WHERE ... (transaction ids, where clause, account)
+    Move(Vec<u32>, Option<Condition>, String),
+
+    /// REPLAY, run the last SELECT statement again
+    Replay,
+
+    /// SAVE, write changes to disk when autosave is disabled
+    Save,
+
+    /// RECONCILE, check recorded balances are consistent with transaction amounts
+    Reconcile,
+
+    /// RELOAD RULES, read auto labelling rules file again
+    ReloadRules,
+
+    /// MERCHANTS WHERE spending > 100 PER MONTH, merchants whose monthly spending exceed a threshold
+    Merchants(Operator, f32),
+
+    /// CREATE VIEW name AS SELECT ..., save a SELECT statement to run again later (name, statement)
+    CreateView(String, String),
+
+    /// SELECT FROM VIEW name, run a saved SELECT statement
+    View(String),
+
+    /// DIFF 'file_path', check which rows of a statement file are already in the database
+    Diff(String),
+
+    /// ACCOUNTS or SHOW ACCOUNTS, list accounts with number of transactions and net amount
+    ListAccounts,
+
+    /// ROLLBACK LAST IMPORT, delete transactions imported by the last IMPORT statement
+    RollbackLastImport,
 }
 
 impl Statement {
-
+    /// Whether the statement modifies the database
+    pub(crate) fn is_mutation(&self) -> bool {
+        match self {
+            Statement::Label(..) | Statement::Insert(..) | Statement::Delete(..) | Statement::Vacuum | Statement::MergeLabel(..) | Statement::Move(..) | Statement::CreateView(..) | Statement::RollbackLastImport => true,
+            Statement::AutoLabel(_, run, _) => *run,
+            Statement::Import(_, dry_run, _, _) => !dry_run,
+            _ => false
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub(crate) enum Projection {
     Star,
     Sum,
+    /// Sum of absolute amounts, i.e. total money moved regardless of direction
+    Turnover,
     Count,
+    /// COUNT(DISTINCT field)
+    CountDistinct(Field),
+    /// Income minus spending
+    Net,
     Auto,
     Id(u32),
 }
 
+/// Transaction field used by projections
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Field {
+    Description,
+    Account,
+    Label,
+}
+
+/// Which amount of a transaction is compared, e.g. spending is the magnitude of negative amounts
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum AmountKind {
+    Spending,
+    Income,
+    Amount,
+}
+
+/// How the amount of a transaction with several labels is added to label totals in `GROUP BY label`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum LabelAmount {
+    /// Full amount is added to every label, so totals of all labels can exceed the total amount
+    Full,
+    /// Amount is divided equally among the labels, `GROUP BY label SPLIT`
+    Split,
+    /// Full amount is added to the first label only, `GROUP BY primary_label`
+    Primary,
+}
+
+/// Order of groups in `GROUP BY label` and `GROUP BY account` results
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum GroupOrder {
+    /// By total amount, spending and income alike, `ORDER BY amount` or `ORDER BY sum` after GROUP BY.
+    /// Largest first if `desc`, which is the default without ORDER BY
+    Amount { desc: bool },
+    /// Alphabetically by label or account, `ORDER BY label` or `ORDER BY account` after GROUP BY
+    Name { desc: bool },
+}
+
 #[derive(Debug, PartialEq)]
 pub(crate) enum GroupBy {
-    None,
-    Label,
+    Label(LabelAmount, GroupOrder),
+    Account(GroupOrder),
+    /// ISO week, e.g. 2023-W05
+    Week,
+    /// Calendar month, e.g. Mar 2023
+    Month,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Condition {
     Id(u32),
+    /// id IN (1, 2, 3)
+    IdIn(Vec<u32>),
     Spending(Operator, f32),
     Income(Operator, f32),
     Amount(Operator, f32),
+    /// Absolute value of amount, i.e. spending or income
+    AbsAmount(Operator, f32),
+    /// Whether amount is whole dollars without cents, e.g. transfers or manual entries
+    WholeAmount(bool),
     Description(Operator, String),
+    /// Description has any of the texts, each one matched as in `desc like '...'`
+    DescriptionAny(Vec<String>),
     /// Start date(inclusive) and end date(exclusive) for the period
     Date(Operator, Range<NaiveDate>),
+    /// Date when transactions were added to database, as a range like Date
+    Imported(Operator, Range<NaiveDate>),
     Label(Operator, String),
+    /// First label of a transaction, '=' or '!='
+    PrimaryLabel(Operator, String),
+    /// label HAS ALL ('a', 'b'), transactions with every one of the labels
+    LabelHasAll(Vec<String>),
+    /// label HAS ANY ('a', 'b'), transactions with at least one of the labels
+    LabelHasAny(Vec<String>),
+    /// Account name, '=' or '!='
+    Account(Operator, String),
+    /// Number of labels of a transaction
+    TagCount(Operator, usize),
+    /// Number of characters in description, e.g. to find parsing problems of a new statement format
+    DescriptionLength(Operator, usize),
+    /// Amount compared to a percentage of the largest amount in the result set, e.g. 'spending > 50% max'
+    PercentOfMax(AmountKind, Operator, f32),
+    /// NOT condition, transactions not matching the condition
+    Not(Box<Condition>),
     And(Box<(Condition, Condition)>),
     Or(Box<(Condition, Condition)>),
 }
@@ -98,6 +255,7 @@ impl OrderBy {
         }
     }
 
+    #[cfg(test)]
     pub(crate) fn amount_desc() -> OrderBy {
         OrderBy {
             field: OrderByField::Amount,
@@ -127,6 +285,9 @@ pub(crate) enum Operator {
     IsNotNull,
     NotEq,
     Between,
+    Regex,
+    StartsWith,
+    EndsWith,
 }
 
 #[derive(Debug, PartialEq)]
@@ -144,25 +305,116 @@ impl From<&str> for Operator {
             "<" => Operator::Lt,
             ">=" => Operator::GtEq,
             "<=" => Operator::LtEq,
+            "!=" => Operator::NotEq,
             "match" | "like" => Operator::Match,
             "between" => Operator::Between,
+            "regex" => Operator::Regex,
             _ => panic!("Unable to parse operator {}", lower_case)
         }
     }
 }
 
 pub(crate) fn parse(query: &str) -> IResult<&str, Statement> {
+    // nom supports up to 21 parsers in one alt, so statements are split into two groups
     alt((
-        select::select,
-        label::parse_label,
-        auto_label::auto_label,
-        export::export,
-        import::import,
-        insert::parse_insert,
-        delete::parse_delete,
+        alt((
+            view::parse_view,
+            view::parse_create_view,
+            select::select,
+            label::parse_label,
+            auto_label::auto_label,
+            export::export,
+            import::import,
+            insert::parse_insert,
+            delete::parse_delete,
+            merge::parse_merge_label,
+            move_account::parse_move,
+        )),
+        alt((
+            audit::parse_log,
+            vacuum::parse_vacuum,
+            budget::parse_budget,
+            similar::parse_similar,
+            suggest::parse_suggest,
+            replay::parse_replay,
+            save::parse_save,
+            reconcile::parse_reconcile,
+            reload::parse_reload_rules,
+            merchants::parse_merchants,
+            diff::parse_diff,
+            accounts::parse_accounts,
+            rollback::parse_rollback_last_import,
+        )),
     ))(query)
 }
 
+/// Remove `-- line` and `/* block */` comments, except inside quoted strings.
+/// Returns `None` if a block comment is not closed yet, e.g. in the middle of a multiline statement.
+pub(crate) fn strip_comments(sql: &str) -> Option<String> {
+    let mut output = String::new();
+    let mut quote = None;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c, chars.peek()) {
+            (Some(q), _, _) => {
+                if c == q {
+                    quote = None;
+                }
+                output.push(c);
+            },
+            (None, '\'' | '"', _) => {
+                quote = Some(c);
+                output.push(c);
+            },
+            (None, '-', Some('-')) => {
+                // Skip to end of line, keeping the line break
+                while chars.next_if(|c| *c != '\n').is_some() {}
+            },
+            (None, '/', Some('*')) => {
+                chars.next();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    if c == '*' && chars.next_if_eq(&'/').is_some() {
+                        closed = true;
+                        break;
+                    }
+                }
+                if !closed {
+                    return None;
+                }
+                // Keep tokens on both sides of the comment apart
+                output.push(' ');
+            },
+            _ => output.push(c),
+        }
+    }
+    Some(output)
+}
+
+/// Split a script into statements separated by ';', except inside quoted strings. Comments should be removed first.
+/// Leading and trailing whitespace of each statement is removed, and empty statements are skipped.
+pub(crate) fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = vec![];
+    let mut current = String::new();
+    let mut quote = None;
+    for c in sql.chars() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ';') => {
+                statements.push(current.trim().to_string());
+                current.clear();
+                continue;
+            },
+            _ => {}
+        }
+        current.push(c);
+    }
+    statements.push(current.trim().to_string());
+    statements.retain(|s| !s.is_empty());
+    statements
+}
+
 pub(crate) fn non_space(input: &str) -> IResult<&str, &str> {
     input.split_at_position_complete(char::is_whitespace)
 }
@@ -210,6 +462,11 @@ fn floating_point_num(input: &str) -> IResult<&str, f32> {
     })?;
 
     match value.parse::<f32>() {
+        // Too many digits overflows to infinity, which breaks SUM and ORDER BY
+        Ok(value) if !value.is_finite() => {
+            warn!("Amount {value} is out of range");
+            Err(nom::Err::Error(nom::error::Error::new(original_input, ErrorKind::Fail)))
+        }
         Ok(value) => Ok((input, value)),
         Err(e) => {
             warn!("{e:?}");
@@ -220,20 +477,55 @@ fn floating_point_num(input: &str) -> IResult<&str, f32> {
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::{parse, Statement};
+    use crate::parser::{floating_point_num, parse, split_statements, strip_comments, Statement};
+
+    #[test]
+    fn test_floating_point_num() {
+        assert_eq!(floating_point_num("-30.45)"), Ok((")", -30.45)));
+        assert!(floating_point_num("nan").is_err());
+        assert!(floating_point_num("inf").is_err());
+        assert!(floating_point_num(&"9".repeat(40)).is_err());
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        assert_eq!(strip_comments("SELECT * -- monthly review"), Some("SELECT * ".to_string()));
+        assert_eq!(strip_comments("SELECT * -- first\nWHERE/* a\nb */month = 3;"), Some("SELECT * \nWHERE month = 3;".to_string()));
+        assert_eq!(strip_comments("SELECT * WHERE desc LIKE 'a--b /* c */'"), Some("SELECT * WHERE desc LIKE 'a--b /* c */'".to_string()));
+        assert_eq!(strip_comments("SELECT * /* not closed\n"), None);
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(split_statements("CREATE VIEW a AS SELECT * WHERE desc LIKE 'x;y';\n\nREPLAY ; SAVE"),
+                   vec!["CREATE VIEW a AS SELECT * WHERE desc LIKE 'x;y'", "REPLAY", "SAVE"]);
+        assert!(split_statements(" ;\n").is_empty());
+    }
 
     #[test]
     fn test() {
         let query = "EXPORT  to './finance/export.csv'";
-        let result = parse(query);
-        println!("{:?}", result);
+        let (_, result) = parse(query).unwrap();
+        assert_eq!(result, Statement::Export("./finance/export.csv".into(), None, None));
 
         let query = "IMPORT";
         let (_, result) = parse(query).unwrap();
-        assert_eq!(result, Statement::Import(false, false));
+        assert_eq!(result, Statement::Import(false, false, false, None));
 
         let query = "IMPORT (i, dryrun)";
         let (_, result) = parse(query).unwrap();
-        assert_eq!(result, Statement::Import(true, true));
+        assert_eq!(result, Statement::Import(true, true, false, None));
+
+        let query = "IMPORT (autolabel)";
+        let (_, result) = parse(query).unwrap();
+        assert_eq!(result, Statement::Import(false, false, true, None));
+
+        let query = "IMPORT (account=cba-savings, dryrun)";
+        let (_, result) = parse(query).unwrap();
+        assert_eq!(result, Statement::Import(false, true, false, Some("cba-savings".into())));
+
+        let query = "rollback  last import";
+        let (_, result) = parse(query).unwrap();
+        assert_eq!(result, Statement::RollbackLastImport);
     }
 }
\ No newline at end of file
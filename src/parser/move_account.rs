@@ -0,0 +1,48 @@
+use log::warn;
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag_no_case};
+use nom::character::complete::{char, multispace0, multispace1};
+use nom::combinator::opt;
+use nom::IResult;
+use nom::error::{Error, ErrorKind};
+use nom::multi::many0;
+use nom::sequence::{delimited, preceded};
+use crate::parser::{non_space1, space_comma1, Statement};
+use crate::parser::condition::where_parser;
+
+/// Parse `MOVE trans_id, trans_id TO account` or `MOVE TO account WHERE ...` pattern.
+/// Transaction ids and WHERE can not be used together.
+pub(crate) fn parse_move(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("MOVE")(input)?;
+    let (input, trans_ids) = many0(preceded(space_comma1, nom::character::complete::u32))(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("TO")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, account) = alt((
+        delimited(char('\''), is_not("'"), char('\'')),
+        non_space1
+    ))(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, condition) = opt(where_parser)(input)?;
+    if !trans_ids.is_empty() && condition.is_some() {
+        warn!("MOVE takes either transaction ids or WHERE, not both");
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::Verify)));
+    }
+    Ok((input, Statement::Move(trans_ids, condition, account.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{Condition, Operator, Statement};
+    use crate::parser::move_account::parse_move;
+
+    #[test]
+    fn test() {
+        assert_eq!(parse_move("move 10, 11 12 to amex"), Ok(("", Statement::Move(vec![10, 11, 12], None, "amex".into()))));
+        assert_eq!(parse_move("MOVE TO 'cba savings' WHERE amount = -10"),
+                   Ok(("", Statement::Move(vec![], Some(Condition::Amount(Operator::Eq, -10.0)), "cba savings".into()))));
+
+        // Ids would be ignored, only transactions matching WHERE would be moved
+        assert!(matches!(parse_move("MOVE 5 6 TO x WHERE amount < 0"), Err(nom::Err::Failure(_))));
+    }
+}
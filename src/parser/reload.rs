@@ -0,0 +1,12 @@
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::multispace1;
+use nom::IResult;
+use crate::parser::Statement;
+
+/// Parse `RELOAD RULES`
+pub(crate) fn parse_reload_rules(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("RELOAD")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("RULES")(input)?;
+    Ok((input, Statement::ReloadRules))
+}
@@ -0,0 +1,14 @@
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::multispace1;
+use nom::IResult;
+use crate::parser::Statement;
+
+/// Parse `ROLLBACK LAST IMPORT`
+pub(crate) fn parse_rollback_last_import(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("ROLLBACK")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("LAST")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag_no_case("IMPORT")(input)?;
+    Ok((input, Statement::RollbackLastImport))
+}
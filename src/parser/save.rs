@@ -0,0 +1,8 @@
+use nom::bytes::complete::tag_no_case;
+use nom::IResult;
+use crate::parser::Statement;
+
+pub(crate) fn parse_save(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("SAVE")(input)?;
+    Ok((input, Statement::Save))
+}
@@ -1,18 +1,19 @@
 use nom::branch::alt;
-use nom::bytes::complete::{is_not, tag_no_case};
-use nom::character::complete::{alpha1, char, multispace0, multispace1, u32};
-use nom::combinator::opt;
+use nom::bytes::complete::{is_not, tag, tag_no_case, take_till1, take_while1};
+use nom::character::complete::{char, multispace0, multispace1, u32};
+use nom::combinator::{opt, value};
 use nom::{IResult};
 use nom::Err::Error;
 use nom::error::ErrorKind;
-use nom::sequence::delimited;
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, tuple};
 
-use crate::parser::{Condition, GroupBy, LogicalOperator, non_space, Operator, OrderBy, OrderByField, Projection, Statement};
+use crate::parser::{comma, Condition, Field, GroupBy, GroupOrder, LabelAmount, LogicalOperator, Operator, OrderBy, OrderByField, Projection, Statement};
 use crate::parser::condition::where_parser;
 
 /// Match `SELECT` statements. This is still working-in-progress. We are trying to migrate
 /// all `SELECT` syntax into this parser.
-
+///
 /// Parse `SELECT *` pattern.
 pub(crate) fn select(input: &str) -> IResult<&str, Statement> {
     let (input, _) = tag_no_case("SELECT")(input)?;
@@ -21,16 +22,20 @@ pub(crate) fn select(input: &str) -> IResult<&str, Statement> {
     // Check if there are special 'where condition' specified here as a projection.
     // E.g. user can do 'SELECT spending WHERE date = 7', it is a shortcut syntax for 'SELECT * WHERE date = 7 AND spending >= 0'
     // let (input, projection_condition) = opt(alt((parse_implied_where_spending, parse_implied_where_income)))(input)?;
+    let (input, top) = opt(parse_top)(input)?;
     let (input, (projection, implied_condition)) = alt((
         parse_star,
         parse_sum,
         parse_count,
+        parse_net,
         parse_implied_where_spending,
         parse_implied_where_income,
         parse_auto,
         parse_trans_id
     ))(input)?;
 
+    let spending = matches!(implied_condition, Some(Condition::Spending(..)));
+
     let (input, account) = opt(from_account)(input)?;
     let (input, condition) = opt(where_parser)(input)?;
     let condition = match condition {
@@ -45,12 +50,31 @@ pub(crate) fn select(input: &str) -> IResult<&str, Statement> {
     let (input, order_by) = parse_order_by(input)?;
     let (input, _) =  multispace0(input)?;
     let (input, limit) = parse_limit(input)?;
+
+    // 'TOP N' / 'BOTTOM N' is a shortcut of 'ORDER BY amount LIMIT N', explicit ORDER BY and LIMIT take precedence.
+    // Biggest spending has the most negative amount, so its order is the reverse of income.
+    let (order_by, limit) = match top {
+        Some((is_top, n)) => {
+            let top_order = OrderBy { field: OrderByField::Amount, desc: is_top != spending };
+            (order_by.unwrap_or(top_order), limit.or(Some(n)))
+        },
+        None => (order_by.unwrap_or(OrderBy::date()), limit)
+    };
     let (input, _) =  multispace0(input)?;
     let (input, group_by) = opt(group_by)(input)?;
     Ok((input, Statement::Select(projection, account, condition, order_by, limit, group_by)))
 }
 
-/// SUM(*), SUM(spending), SUM(income)
+/// TOP N, BOTTOM N. Returns true for TOP, and the number of transactions.
+fn parse_top(input: &str) -> IResult<&str, (bool, usize)> {
+    let (input, is_top) = alt((value(true, tag_no_case("TOP")), value(false, tag_no_case("BOTTOM"))))(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, n) = u32(input)?;
+    let (input, _) = multispace1(input)?;
+    Ok((input, (is_top, n as usize)))
+}
+
+/// SUM(*), SUM(spending), SUM(income), SUM(turnover)
 fn parse_sum(input: &str) -> IResult<&str, (Projection, Option<Condition>)> {
     let (input, _) = tag_no_case("SUM")(input)?;
     let (input, sum_arg) = delimited(char('('), is_not(")"), char(')'))(input)?;
@@ -58,22 +82,43 @@ fn parse_sum(input: &str) -> IResult<&str, (Projection, Option<Condition>)> {
     match sum_arg.to_lowercase().as_str() {
         "spending" => Ok((input, (Projection::Sum, Some(Condition::Spending(Operator::GtEq, 0.0))))),
         "income" => Ok((input, (Projection::Sum, Some(Condition::Income(Operator::GtEq, 0.0))))),
+        "turnover" => Ok((input, (Projection::Turnover, None))),
         _ => Ok((input, (Projection::Sum, None)))
     }
 }
 
-/// COUNT(*), COUNT(spending), COUNT(income)
+/// COUNT(*), COUNT(spending), COUNT(income), COUNT(DISTINCT description|account|label)
 fn parse_count(input: &str) -> IResult<&str, (Projection, Option<Condition>)> {
     let (input, _) = tag_no_case("COUNT")(input)?;
     let (input, count_arg) = delimited(char('('), is_not(")"), char(')'))(input)?;
     let (input, _) =  multispace0(input)?;
-    match count_arg.to_lowercase().as_str() {
+    let count_arg = count_arg.trim().to_lowercase();
+    if let Some(field) = count_arg.strip_prefix("distinct ") {
+        let field = match field.trim() {
+            "description" | "desc" => Field::Description,
+            "account" => Field::Account,
+            "label" => Field::Label,
+            // TODO fix the error handling
+            _ => return Err(Error(nom::error::Error { input, code: ErrorKind::Fail }))
+        };
+        return Ok((input, (Projection::CountDistinct(field), None)));
+    }
+
+    match count_arg.as_str() {
         "spending" => Ok((input, (Projection::Count, Some(Condition::Spending(Operator::GtEq, 0.0))))),
         "income" => Ok((input, (Projection::Count, Some(Condition::Income(Operator::GtEq, 0.0))))),
         _ => Ok((input, (Projection::Count, None)))
     }
 }
 
+/// NET, NET(*)
+fn parse_net(input: &str) -> IResult<&str, (Projection, Option<Condition>)> {
+    let (input, _) = tag_no_case("NET")(input)?;
+    let (input, _) = opt(tag("(*)"))(input)?;
+    let (input, _) =  multispace0(input)?;
+    Ok((input, (Projection::Net, None)))
+}
+
 /// Normal projection, SELECT * ...
 fn parse_star(input: &str) -> IResult<&str, (Projection, Option<Condition>)> {
     let (input, _) = tag_no_case("*")(input)?;
@@ -109,35 +154,70 @@ fn parse_trans_id(input: &str) -> IResult<&str, (Projection, Option<Condition>)>
     Ok((input, (Projection::Id(trans_id), None)))
 }
 
-/// FROM account
-pub(crate) fn from_account(input: &str) -> IResult<&str, String> {
+/// FROM account, account
+pub(crate) fn from_account(input: &str) -> IResult<&str, Vec<String>> {
     let (input, _) = tag_no_case("FROM")(input)?;
     let (input, _) = multispace1(input)?;
-    let (input, account) = non_space(input)?;
+    let (input, accounts) = separated_list1(comma, take_till1(|c: char| c.is_whitespace() || c == ','))(input)?;
     let (input, _) = multispace0(input)?;
-    Ok((input, account.into()))
+    Ok((input, accounts.into_iter().map(String::from).collect()))
 }
 
-fn group_by(input: &str) -> IResult<&str, GroupBy> {
+pub(crate) fn group_by(input: &str) -> IResult<&str, GroupBy> {
     let (input, _) = tag_no_case("group by")(input)?;
     let (input, _) =  multispace1(input)?;
-    let (input, group_by_value) = alpha1(input)?;
+    let (input, group_by_value) = take_while1(|c: char| c.is_alphanumeric() || c == '_')(input)?;
     match group_by_value {
-        "label" => Ok((input, GroupBy::Label)),
+        "label" => {
+            let (input, split) = opt(tuple((multispace1, tag_no_case("SPLIT"))))(input)?;
+            let label_amount = if split.is_some() { LabelAmount::Split } else { LabelAmount::Full };
+            let (input, order) = group_order("label")(input)?;
+            Ok((input, GroupBy::Label(label_amount, order)))
+        },
+        "primary_label" => {
+            let (input, order) = group_order("label")(input)?;
+            Ok((input, GroupBy::Label(LabelAmount::Primary, order)))
+        },
+        "account" => {
+            let (input, order) = group_order("account")(input)?;
+            Ok((input, GroupBy::Account(order)))
+        },
+        "week" => Ok((input, GroupBy::Week)),
+        "month" => Ok((input, GroupBy::Month)),
         // TODO fix the error handling
         _ => Err(Error(nom::error::Error { input, code: ErrorKind::Fail }))
     }
 }
 
-fn parse_order_by(input: &str) -> IResult<&str, OrderBy> {
+/// Optional `ORDER BY amount`, `ORDER BY sum` or `ORDER BY name_field` after `GROUP BY`, followed by `ASC` or `DESC`.
+/// Groups are ordered by amount, largest first, by default.
+fn group_order<'a>(name_field: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, GroupOrder> {
+    move |input| {
+        let (input, order) = opt(tuple((
+            multispace1,
+            tag_no_case("order by"),
+            multispace1,
+            alt((value(true, alt((tag_no_case("amount"), tag_no_case("sum")))), value(false, tag_no_case(name_field)))),
+            opt(tuple((multispace1, alt((value(true, tag_no_case("desc")), value(false, tag_no_case("asc"))))))),
+        )))(input)?;
+        let order = match order {
+            Some((_, _, _, true, desc)) => GroupOrder::Amount { desc: desc.is_some_and(|(_, desc)| desc) },
+            Some((_, _, _, false, desc)) => GroupOrder::Name { desc: desc.is_some_and(|(_, desc)| desc) },
+            None => GroupOrder::Amount { desc: true },
+        };
+        Ok((input, order))
+    }
+}
+
+fn parse_order_by(input: &str) -> IResult<&str, Option<OrderBy>> {
     let (input, order_by_clause) = opt(tag_no_case("order by"))(input)?;
     match order_by_clause {
-        None => Ok((input, OrderBy::date())),
+        None => Ok((input, None)),
         Some(_) => {
             let (input, _) =  multispace1(input)?;
             let (input, field) = alt((order_by_date, order_by_amount))(input)?;
             let (input, desc) = opt(tag_no_case("desc"))(input)?;
-            Ok((input, OrderBy { field, desc: desc.is_some() }))
+            Ok((input, Some(OrderBy { field, desc: desc.is_some() })))
         }
     }
 }
@@ -169,7 +249,7 @@ fn parse_limit(input: &str) -> IResult<&str, Option<usize>> {
 #[cfg(test)]
 mod tests {
     use crate::parser::select::{select};
-    use crate::parser::{Condition, GroupBy, Operator, OrderBy, Projection, Statement};
+    use crate::parser::{Condition, Field, GroupBy, GroupOrder, LabelAmount, Operator, OrderBy, OrderByField, Projection, Statement};
 
     #[test]
     fn test() {
@@ -183,12 +263,16 @@ mod tests {
 
         let query = "SELECT * FROM amex-plat LIMIT 5";
         let result = select(query);
-        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, Some("amex-plat".into()), None, OrderBy::date(), Some(5), None))));
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, Some(vec!["amex-plat".into()]), None, OrderBy::date(), Some(5), None))));
+
 
+        let query = "SELECT * FROM amex, cba-savings,westpac";
+        let result = select(query);
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, Some(vec!["amex".into(), "cba-savings".into(), "westpac".into()]), None, OrderBy::date(), None, None))));
 
         let query = "SELECT SUM(spending) from cba";
         let result = select(query);
-        assert_eq!(result, Ok(("", Statement::Select(Projection::Sum, Some("cba".into()), Some(Condition::Spending(Operator::GtEq, 0.0)), OrderBy::date(), None, None))));
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Sum, Some(vec!["cba".into()]), Some(Condition::Spending(Operator::GtEq, 0.0)), OrderBy::date(), None, None))));
 
         let query = "SELECT sum(income)";
         let result = select(query);
@@ -198,16 +282,87 @@ mod tests {
         let result = select(query);
         assert_eq!(result, Ok(("", Statement::Select(Projection::Count, None, None, OrderBy::date(), None, None))));
 
+        let query = "select count(distinct description) where month = 2023-03";
+        let result = select(query);
+        assert!(matches!(result, Ok(("", Statement::Select(Projection::CountDistinct(Field::Description), None, Some(Condition::Date(..)), _, None, None)))));
+
+        let query = "select count( DISTINCT label )";
+        let result = select(query);
+        assert_eq!(result, Ok(("", Statement::Select(Projection::CountDistinct(Field::Label), None, None, OrderBy::date(), None, None))));
+
+        assert!(select("select count(distinct amount)").is_err());
+
+        let query = "select net from cba";
+        let result = select(query);
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Net, Some(vec!["cba".into()]), None, OrderBy::date(), None, None))));
+
+        let query = "SELECT NET(*) WHERE year = 2023";
+        let result = select(query);
+        assert!(matches!(result, Ok(("", Statement::Select(Projection::Net, None, Some(Condition::Date(..)), _, None, None)))));
+
         let query = "select count(spending) from cba where spending < 100.0 limit 4 group by label";
         let result = select(query);
         assert_eq!(result, Ok(("", Statement::Select(
             Projection::Count,
-            Some("cba".into()),
+            Some(vec!["cba".into()]),
             Some(Condition::And(Box::new((Condition::Spending(Operator::Lt, 100.0), Condition::Spending(Operator::GtEq, 0.0))))),
-            OrderBy::date(), Some(4), Some(GroupBy::Label)))));
+            OrderBy::date(), Some(4), Some(GroupBy::Label(LabelAmount::Full, GroupOrder::Amount { desc: true }))))));
+
+        let query = "select * where year = 2023 group by month";
+        let result = select(query);
+        assert!(matches!(result, Ok(("", Statement::Select(Projection::Star, None, Some(Condition::Date(..)), _, None, Some(GroupBy::Month))))));
+
+        let query = "select * group by primary_label";
+        let result = select(query);
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, None, None, OrderBy::date(), None, Some(GroupBy::Label(LabelAmount::Primary, GroupOrder::Amount { desc: true }))))));
+
+        let query = "select spending limit 5 group by label split";
+        let result = select(query);
+        assert!(matches!(result, Ok(("", Statement::Select(_, None, _, _, Some(5), Some(GroupBy::Label(LabelAmount::Split, GroupOrder::Amount { desc: true })))))));
+
+        let query = "select * group by account order by account";
+        let result = select(query);
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, None, None, OrderBy::date(), None, Some(GroupBy::Account(GroupOrder::Name { desc: false }))))));
+
+        let query = "select * group by label split ORDER BY label";
+        let result = select(query);
+        assert!(matches!(result, Ok(("", Statement::Select(.., Some(GroupBy::Label(LabelAmount::Split, GroupOrder::Name { desc: false })))))));
+
+        let query = "SELECT SUM(*) WHERE year = 2023 GROUP BY account ORDER BY sum";
+        let result = select(query);
+        assert!(matches!(result, Ok(("", Statement::Select(Projection::Sum, .., Some(GroupBy::Account(GroupOrder::Amount { desc: false })))))));
+
+        let query = "select spending group by label order by amount desc";
+        let result = select(query);
+        assert!(matches!(result, Ok(("", Statement::Select(.., Some(GroupBy::Label(LabelAmount::Full, GroupOrder::Amount { desc: true })))))));
+
+        let query = "select * group by account order by account DESC";
+        let result = select(query);
+        assert!(matches!(result, Ok(("", Statement::Select(.., Some(GroupBy::Account(GroupOrder::Name { desc: true })))))));
+
+        let query = "select spending group by week";
+        let result = select(query);
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, None, Some(Condition::Spending(Operator::GtEq, 0.0)), OrderBy::date(), None, Some(GroupBy::Week)))));
 
         let query = "select * from cba where spending > 100.0 order by amount desc group by label";
         let result = select(query);
-        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, Some("cba".into()), Some(Condition::Spending(Operator::Gt, 100.0)), OrderBy::amount_desc(), None, Some(GroupBy::Label)))));
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, Some(vec!["cba".into()]), Some(Condition::Spending(Operator::Gt, 100.0)), OrderBy::amount_desc(), None, Some(GroupBy::Label(LabelAmount::Full, GroupOrder::Amount { desc: true }))))));
+    }
+
+    #[test]
+    fn test_top() {
+        let ascending = OrderBy { field: OrderByField::Amount, desc: false };
+
+        let result = select("SELECT TOP 10 spending");
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, None, Some(Condition::Spending(Operator::GtEq, 0.0)), ascending, Some(10), None))));
+
+        let result = select("select top 3 income from cba");
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, Some(vec!["cba".into()]), Some(Condition::Income(Operator::GtEq, 0.0)), OrderBy::amount_desc(), Some(3), None))));
+
+        let result = select("select bottom 5 spending where year = 2023");
+        assert!(matches!(result, Ok(("", Statement::Select(Projection::Star, None, Some(Condition::And(..)), OrderBy { field: OrderByField::Amount, desc: true }, Some(5), None)))));
+
+        let result = select("select top 5 * limit 2");
+        assert_eq!(result, Ok(("", Statement::Select(Projection::Star, None, None, OrderBy::amount_desc(), Some(2), None))));
     }
 }
\ No newline at end of file
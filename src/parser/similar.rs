@@ -0,0 +1,28 @@
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::{multispace0, multispace1, u32, u64};
+use nom::combinator::opt;
+use nom::IResult;
+use nom::sequence::{preceded, tuple};
+use crate::parser::Statement;
+
+/// Parse `SIMILAR 123 [LIMIT 5]` pattern.
+pub(crate) fn parse_similar(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("SIMILAR")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, trans_id) = u32(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, limit) = opt(preceded(tuple((tag_no_case("LIMIT"), multispace1)), u64))(input)?;
+    Ok((input, Statement::Similar(trans_id, limit.map(|l| l as usize))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::similar::parse_similar;
+    use crate::parser::Statement;
+
+    #[test]
+    fn test() {
+        assert_eq!(parse_similar("similar 12"), Ok(("", Statement::Similar(12, None))));
+        assert_eq!(parse_similar("SIMILAR 12 limit 3"), Ok(("", Statement::Similar(12, Some(3)))));
+    }
+}
@@ -0,0 +1,12 @@
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::{multispace1, u32};
+use nom::IResult;
+use crate::parser::Statement;
+
+/// Parse `SUGGEST 123` pattern.
+pub(crate) fn parse_suggest(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tag_no_case("SUGGEST")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, trans_id) = u32(input)?;
+    Ok((input, Statement::Suggest(trans_id)))
+}
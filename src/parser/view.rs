@@ -0,0 +1,45 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag_no_case, take_till1};
+use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::recognize;
+use nom::IResult;
+use nom::sequence::tuple;
+use crate::parser::select::select;
+use crate::parser::Statement;
+
+/// Parse `CREATE VIEW name AS SELECT ...` pattern. The SELECT statement is kept as text.
+pub(crate) fn parse_create_view(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tuple((tag_no_case("CREATE"), multispace1, tag_no_case("VIEW"), multispace1))(input)?;
+    let (input, name) = take_till1(char::is_whitespace)(input)?;
+    let (input, _) = tuple((multispace1, tag_no_case("AS"), multispace1))(input)?;
+    let (input, sql) = recognize(select)(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, Statement::CreateView(name.to_string(), sql.to_string())))
+}
+
+/// Parse `SELECT FROM VIEW name` or `VIEW name` pattern.
+pub(crate) fn parse_view(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = alt((
+        recognize(tuple((tag_no_case("SELECT"), multispace1, tag_no_case("FROM"), multispace1, tag_no_case("VIEW")))),
+        tag_no_case("VIEW")
+    ))(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, name) = take_till1(char::is_whitespace)(input)?;
+    Ok((input, Statement::View(name.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::view::{parse_create_view, parse_view};
+    use crate::parser::Statement;
+
+    #[test]
+    fn test() {
+        assert_eq!(parse_create_view("CREATE VIEW dining AS SELECT spending WHERE label = 'dining' GROUP BY week"),
+                   Ok(("", Statement::CreateView("dining".into(), "SELECT spending WHERE label = 'dining' GROUP BY week".into()))));
+        assert!(parse_create_view("CREATE VIEW dining AS DELETE 1").is_err());
+
+        assert_eq!(parse_view("SELECT FROM VIEW dining"), Ok(("", Statement::View("dining".into()))));
+        assert_eq!(parse_view("view dining"), Ok(("", Statement::View("dining".into()))));
+    }
+}
@@ -4,7 +4,7 @@ use chrono::NaiveDateTime;
 use serde::Serializer;
 
 /// Hold transaction info returned from database select
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub(crate) struct Transaction {
     #[serde(alias = "_perfidb_transaction_id", rename(serialize = "_perfidb_transaction_id"))]
     pub(crate) id: u32,
@@ -15,6 +15,13 @@ pub(crate) struct Transaction {
     pub(crate) amount: f32,
     #[serde(serialize_with = "serialise_labels", rename(serialize = "_perfidb_label"))]
     pub(crate) labels: Vec<String>,
+    #[serde(skip)]
+    pub(crate) currency: Option<String>,
+    #[serde(skip)]
+    pub(crate) balance: Option<f32>,
+    /// Amount as in the statement if it was inverted during import
+    #[serde(skip)]
+    pub(crate) raw_amount: Option<f32>,
 }
 
 impl Transaction {
@@ -27,6 +34,9 @@ impl Transaction {
             description,
             amount,
             labels: tags,
+            currency: None,
+            balance: None,
+            raw_amount: None,
         }
     }
 
@@ -1,5 +1,12 @@
+use std::io::{stdout, IsTerminal};
 use std::ops::Range;
 use chrono::{Datelike, NaiveDate, Utc};
+use crossterm::style::Stylize;
+use crossterm::terminal;
+use crate::config::Config;
+
+/// Descriptions are never cut shorter than this to fit the terminal
+const MIN_DESCRIPTION_WIDTH: usize = 10;
 
 pub(crate) fn year_of(year: i32) -> Range<NaiveDate> {
     let first_day = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
@@ -10,17 +17,54 @@ pub(crate) fn year_of(year: i32) -> Range<NaiveDate> {
 /// Compute month from an int, based on current date. If the month given is in future return the
 /// same month in last year. E.g. if now is 2024-03, input 6 will return 2023-06.
 pub(crate) fn month_of(month: u32) -> Range<NaiveDate> {
+    month_relative_to(month, Utc::now().naive_utc().date())
+}
+
+/// Same as [month_of], using the given date as today
+fn month_relative_to(month: u32, today: NaiveDate) -> Range<NaiveDate> {
     let mut month = month % 12;
     if month == 0 {
         month = 12;
     }
 
+    let mut year = today.year();
+    // Current month is in current year
+    if month > today.month() {
+        year -= 1;
+    }
+
+    year_month_of(year, month)
+}
+
+/// Compute quarter from an int, based on current date. If the quarter given is in future return the
+/// same quarter in last year. E.g. if now is 2024-03, input 2 will return 2023-Q2.
+pub(crate) fn quarter_of(quarter: u32) -> Range<NaiveDate> {
     let today = Utc::now().naive_utc().date();
     let mut year = today.year();
-    if month >= today.month() {
+    if quarter > (today.month() - 1) / 3 + 1 {
         year -= 1;
     }
 
+    year_quarter_of(year, quarter)
+}
+
+/// Date range of the given quarter (1 to 4) in the given year
+pub(crate) fn year_quarter_of(year: i32, quarter: u32) -> Range<NaiveDate> {
+    let first_month = (quarter - 1) * 3 + 1;
+    let first_day = NaiveDate::from_ymd_opt(year, first_month, 1).unwrap();
+    let last_month = year_month_of(year, first_month + 2);
+
+    first_day..last_month.end
+}
+
+/// Date range of the current month
+pub(crate) fn current_month() -> Range<NaiveDate> {
+    let today = Utc::now().naive_utc().date();
+    year_month_of(today.year(), today.month())
+}
+
+/// Date range of the given month in the given year
+pub(crate) fn year_month_of(year: i32, month: u32) -> Range<NaiveDate> {
     let first_day = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
     let next_month = if month == 12 { 1 } else { month + 1 };
     let next_month_year = if month == 12 { year + 1 } else { year };
@@ -28,3 +72,78 @@ pub(crate) fn month_of(month: u32) -> Range<NaiveDate> {
 
     first_day..first_day_next_month
 }
+
+/// Shorten text to at most `width` characters, replacing the end with '…' if it is too long
+pub(crate) fn truncate_with_ellipsis(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Labels separated by ', ', each in its colour from config
+pub(crate) fn colored_labels(labels: &[String], config: &Config) -> String {
+    labels.iter()
+        .map(|label| label.as_str().with(config.label_color(label)).to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Reduce the description width so a row fits in the terminal, given the other columns take `reserved` characters
+pub(crate) fn fit_terminal_width(width: usize, reserved: usize) -> usize {
+    match terminal::size() {
+        Ok((columns, _)) if stdout().is_terminal() => width.min((columns as usize).saturating_sub(reserved)).max(MIN_DESCRIPTION_WIDTH),
+        _ => width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, NaiveDate, Utc};
+    use crate::util::{month_relative_to, quarter_of, truncate_with_ellipsis, year_month_of, year_quarter_of};
+
+    #[test]
+    fn test_truncate_with_ellipsis() {
+        assert_eq!(truncate_with_ellipsis("Coffee", 6), "Coffee");
+        assert_eq!(truncate_with_ellipsis("Coffee", 5), "Coff…");
+
+        // Byte 49 falls inside 'é', which used to panic when slicing bytes
+        let description = format!("{}éclair Crème Brûlée", "a".repeat(48));
+        assert!(!description.is_char_boundary(49));
+        assert_eq!(truncate_with_ellipsis(&description, 50), format!("{}é…", "a".repeat(48)));
+
+        // Description already containing the ellipsis character
+        assert_eq!(truncate_with_ellipsis("Café…Bar…", 6), "Café……");
+        assert_eq!(truncate_with_ellipsis("Café…Bar…", 9), "Café…Bar…");
+    }
+
+    #[test]
+    fn test_year_quarter_of() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(year_quarter_of(2023, 1), date(2023, 1, 1)..date(2023, 4, 1));
+        assert_eq!(year_quarter_of(2023, 2), date(2023, 4, 1)..date(2023, 7, 1));
+        assert_eq!(year_quarter_of(2023, 3), date(2023, 7, 1)..date(2023, 10, 1));
+        assert_eq!(year_quarter_of(2023, 4), date(2023, 10, 1)..date(2024, 1, 1));
+    }
+
+    #[test]
+    fn test_month_relative_to() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        assert_eq!(month_relative_to(3, today), year_month_of(2024, 3));
+        assert_eq!(month_relative_to(2, today), year_month_of(2024, 2));
+        assert_eq!(month_relative_to(6, today), year_month_of(2023, 6));
+        assert_eq!(month_relative_to(12, today), year_month_of(2023, 12));
+    }
+
+    #[test]
+    fn test_quarter_of() {
+        let today = Utc::now().naive_utc().date();
+        let current_quarter = (today.month() - 1) / 3 + 1;
+        assert_eq!(quarter_of(current_quarter), year_quarter_of(today.year(), current_quarter));
+        if current_quarter < 4 {
+            assert_eq!(quarter_of(4), year_quarter_of(today.year() - 1, 4));
+        }
+    }
+}